@@ -0,0 +1,245 @@
+//! Diff backends, these compute the alignment between source and target from which the edit
+//! script is built.
+
+use std::str::FromStr;
+
+use crate::myers::{self, DiffOp};
+use crate::ParseError;
+
+/// An algorithm that aligns a source and a target string. The alignment is returned as a list of
+/// operations in forward order, with byte ranges that start and end on character boundaries.
+/// Every character of the source and of the target must be covered by exactly one operation.
+pub trait DiffBackend {
+    fn diff(&self, source: &str, target: &str) -> Vec<DiffOp>;
+}
+
+/// The diff algorithm of the `dissimilar` crate; Myers' algorithm with a semantic cleanup that
+/// favours human-readable edit scripts over minimal ones
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct DissimilarBackend;
+
+/// Myers' algorithm without any cleanup, yields a minimal number of inserted and deleted
+/// characters
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MyersBackend;
+
+/// A Needleman-Wunsch alignment with Levenshtein costs, yields a minimal number of inserted,
+/// deleted and substituted characters
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct LevenshteinBackend;
+
+impl DiffBackend for DissimilarBackend {
+    fn diff(&self, source: &str, target: &str) -> Vec<DiffOp> {
+        let mut ops = Vec::new();
+        let (mut sourcepos, mut targetpos) = (0, 0);
+        for chunk in dissimilar::diff(source, target) {
+            match chunk {
+                dissimilar::Chunk::Equal(s) => {
+                    ops.push(DiffOp::Equal(
+                        sourcepos..sourcepos + s.len(),
+                        targetpos..targetpos + s.len(),
+                    ));
+                    sourcepos += s.len();
+                    targetpos += s.len();
+                }
+                dissimilar::Chunk::Delete(s) => {
+                    ops.push(DiffOp::Delete(sourcepos..sourcepos + s.len()));
+                    sourcepos += s.len();
+                }
+                dissimilar::Chunk::Insert(s) => {
+                    ops.push(DiffOp::Insert(targetpos..targetpos + s.len()));
+                    targetpos += s.len();
+                }
+            }
+        }
+        ops
+    }
+}
+
+impl DiffBackend for MyersBackend {
+    fn diff(&self, source: &str, target: &str) -> Vec<DiffOp> {
+        let sourcechars: Vec<char> = source.chars().collect();
+        let targetchars: Vec<char> = target.chars().collect();
+        let ops = myers::diff(&sourcechars, &targetchars);
+        to_byte_ranges(source, target, ops)
+    }
+}
+
+impl DiffBackend for LevenshteinBackend {
+    fn diff(&self, source: &str, target: &str) -> Vec<DiffOp> {
+        let a: Vec<char> = source.chars().collect();
+        let b: Vec<char> = target.chars().collect();
+        let width = b.len() + 1;
+        let mut matrix = vec![0u32; (a.len() + 1) * width];
+        for i in 0..=a.len() {
+            for j in 0..=b.len() {
+                matrix[i * width + j] = if i == 0 {
+                    j as u32
+                } else if j == 0 {
+                    i as u32
+                } else {
+                    let substitution = (a[i - 1] != b[j - 1]) as u32;
+                    (matrix[(i - 1) * width + j - 1] + substitution)
+                        .min(matrix[(i - 1) * width + j] + 1)
+                        .min(matrix[i * width + j - 1] + 1)
+                };
+            }
+        }
+
+        //backtrack from the end, preferring matches and substitutions over deletions and
+        //deletions over insertions
+        let mut ops = Vec::new();
+        let (mut i, mut j) = (a.len(), b.len());
+        while i > 0 || j > 0 {
+            let cost = matrix[i * width + j];
+            if i > 0 && j > 0 {
+                let substitution = (a[i - 1] != b[j - 1]) as u32;
+                if matrix[(i - 1) * width + j - 1] + substitution == cost {
+                    i -= 1;
+                    j -= 1;
+                    ops.push(if substitution == 0 {
+                        Edit::Equal(i, j)
+                    } else {
+                        Edit::Substitute(i, j)
+                    });
+                    continue;
+                }
+            }
+            if i > 0 && matrix[(i - 1) * width + j] + 1 == cost {
+                i -= 1;
+                ops.push(Edit::Delete(i));
+            } else {
+                j -= 1;
+                ops.push(Edit::Insert(j));
+            }
+        }
+        ops.reverse();
+        to_byte_ranges(source, target, group_edits(ops))
+    }
+}
+
+/// A single edit in an alignment, with character indices in source and target
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Edit {
+    Equal(usize, usize),
+    Substitute(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Groups consecutive edits of the same kind. A run of substitutions becomes a deletion followed
+/// by an insertion of the same length, which is expressed as a single substitution in the edit
+/// script. Runs of different kinds are kept apart so the distance of the edit script matches the
+/// Levenshtein distance.
+fn group_edits(edits: Vec<Edit>) -> Vec<DiffOp> {
+    let mut ops: Vec<DiffOp> = Vec::new();
+    let mut previous: Option<Edit> = None;
+    for edit in edits {
+        let extends = matches!(
+            (previous, edit),
+            (Some(Edit::Equal(..)), Edit::Equal(..))
+                | (Some(Edit::Substitute(..)), Edit::Substitute(..))
+                | (Some(Edit::Delete(_)), Edit::Delete(_))
+                | (Some(Edit::Insert(_)), Edit::Insert(_))
+        );
+        let len = ops.len();
+        match (edit, extends) {
+            (Edit::Equal(i, j), true) => {
+                if let Some(DiffOp::Equal(s, t)) = ops.last_mut() {
+                    s.end = i + 1;
+                    t.end = j + 1;
+                }
+            }
+            (Edit::Equal(i, j), false) => ops.push(DiffOp::Equal(i..i + 1, j..j + 1)),
+            (Edit::Substitute(i, j), true) => {
+                if let DiffOp::Delete(s) = &mut ops[len - 2] {
+                    s.end = i + 1;
+                }
+                if let DiffOp::Insert(t) = &mut ops[len - 1] {
+                    t.end = j + 1;
+                }
+            }
+            (Edit::Substitute(i, j), false) => {
+                ops.push(DiffOp::Delete(i..i + 1));
+                ops.push(DiffOp::Insert(j..j + 1));
+            }
+            (Edit::Delete(i), true) => {
+                if let Some(DiffOp::Delete(s)) = ops.last_mut() {
+                    s.end = i + 1;
+                }
+            }
+            (Edit::Delete(i), false) => ops.push(DiffOp::Delete(i..i + 1)),
+            (Edit::Insert(j), true) => {
+                if let Some(DiffOp::Insert(t)) = ops.last_mut() {
+                    t.end = j + 1;
+                }
+            }
+            (Edit::Insert(j), false) => ops.push(DiffOp::Insert(j..j + 1)),
+        }
+        previous = Some(edit);
+    }
+    ops
+}
+
+/// Converts operations with character indices to operations with byte ranges
+pub(crate) fn to_byte_ranges(source: &str, target: &str, ops: Vec<DiffOp>) -> Vec<DiffOp> {
+    let offsets = |s: &str| -> Vec<usize> {
+        s.char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(s.len()))
+            .collect()
+    };
+    let sourceoffsets = offsets(source);
+    let targetoffsets = offsets(target);
+    ops.into_iter()
+        .map(|op| match op {
+            DiffOp::Equal(s, t) => DiffOp::Equal(
+                sourceoffsets[s.start]..sourceoffsets[s.end],
+                targetoffsets[t.start]..targetoffsets[t.end],
+            ),
+            DiffOp::Delete(s) => DiffOp::Delete(sourceoffsets[s.start]..sourceoffsets[s.end]),
+            DiffOp::Insert(t) => DiffOp::Insert(targetoffsets[t.start]..targetoffsets[t.end]),
+        })
+        .collect()
+}
+
+/// The available diff algorithms
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum Algorithm {
+    /// The diff algorithm of the `dissimilar` crate, see [`DissimilarBackend`]
+    #[default]
+    Dissimilar,
+
+    /// Myers' algorithm without cleanup, see [`MyersBackend`]
+    Myers,
+
+    /// Levenshtein alignment, see [`LevenshteinBackend`]
+    Levenshtein,
+}
+
+impl Algorithm {
+    /// Returns the backend that implements the algorithm
+    pub fn backend(&self) -> &'static dyn DiffBackend {
+        match self {
+            Algorithm::Dissimilar => &DissimilarBackend,
+            Algorithm::Myers => &MyersBackend,
+            Algorithm::Levenshtein => &LevenshteinBackend,
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "dissimilar" => Ok(Algorithm::Dissimilar),
+            "myers" => Ok(Algorithm::Myers),
+            "levenshtein" => Ok(Algorithm::Levenshtein),
+            _ => Err(ParseError::WithMessage(format!(
+                "Unknown diff algorithm: {} (expected dissimilar, myers or levenshtein)",
+                s
+            ))),
+        }
+    }
+}
@@ -23,6 +23,19 @@ pub fn printeditstringlength(s: &str, op: char, suffix: bool) {
     }
 }
 
+/// Formats an error in parsing an edit script, with a caret pointing at the problem
+fn parse_error_message(editscript: &str, err: &ParseError) -> String {
+    match err {
+        ParseError::Syntax { charoffset, .. } => format!(
+            "{}\n    {}\n    {}^",
+            err,
+            editscript,
+            " ".repeat(*charoffset)
+        ),
+        err => err.to_string(),
+    }
+}
+
 fn main() {
     let args = App::new("sesdiff")
         .version("0.3.1") //also adapt in cargo.toml
@@ -42,24 +55,209 @@ fn main() {
         .arg(Arg::with_name("nosubstitutions")
             .long("nosub")
             .short("S")
-            .help("Do not count substittutions/transpositions in the edit distance")
+            .help("Do not allow substitutions, they are expressed as a deletion and an insertion instead and both count towards the edit distance")
             )
         .arg(Arg::with_name("abstract")
             .long("abstract")
             .short("a")
             .help("Attempt to generate more abstract edit scripts by not explicitly registering unchanged parts, but referring to them by their length only")
             )
+        .arg(Arg::with_name("context")
+            .long("context")
+            .short("k")
+            .takes_value(true)
+            .value_name("N")
+            .help("Keep at most N characters of unchanged parts literally, adjacent to the changes, and refer to the rest by their length only; yields edit scripts that are more abstract than in normal mode but more specific than with --abstract")
+            )
+        .arg(Arg::with_name("wildcards")
+            .long("wildcards")
+            .short("w")
+            .help("Express unchanged parts at the start and end that are referred to by their length only (with --abstract or --context) as a wildcard (=[*]) that matches any length, so edit scripts apply to strings of any length")
+            )
+        .arg(Arg::with_name("captures")
+            .long("captures")
+            .help("Express insertions that copy unchanged material, such as reduplications (buku -> buku-buku), as a back-reference (&[1]) to a captured unchanged part (=1[buku])")
+            )
         .arg(Arg::with_name("apply")
             .long("apply")
             .short("A")
             .help("Apply mode; apply the edit scripts from the second column to the strings in the first column"))
+        .arg(Arg::with_name("trace")
+            .long("trace")
+            .help("Used with --apply: after every line, print a trace of what each instruction of the edit script consumed and produced, along with the state (head and tail) after it")
+            )
+        .arg(Arg::with_name("all")
+            .long("all")
+            .help("Used with --apply: output every distinct result the edit script can produce (separated by |), exploring all alternatives of options (including insertions) and classes, and in infix mode all the ways to match the edit script")
+            )
+        .arg(Arg::with_name("matching")
+            .long("matching")
+            .takes_value(true)
+            .possible_values(&["first", "longest", "shortest"])
+            .help("Used with --apply: policy for choosing between the alternatives of options (e.g. -[n|en]) when more than one matches: the first listed, the longest or the shortest. The next alternative is tried if the preferred one makes the rest of the edit script fail")
+            )
+        .arg(Arg::with_name("invert")
+            .long("invert")
+            .short("I")
+            .help("Invert mode; output the inverse of the edit scripts from the second column, which transform the strings in the target back to the source. Combine with --apply to apply the inverted edit scripts to the strings in the first column"))
+        .arg(Arg::with_name("compose")
+            .long("compose")
+            .help("Compose mode; compose the edit scripts in the first column (from A to B) with those in the second column (from B to C) into a single edit script (from A to C). Use --suffix or --prefix if the edit scripts are in that mode"))
         .arg(Arg::with_name("infix")
             .long("infix")
             .short("i")
             .help("Infix mode, used only with --apply: searches all occurrences of the editscript in the string and replaces them all")
             )
+        .arg(Arg::with_name("graphemes")
+            .long("graphemes")
+            .short("g")
+            .help("Operate on grapheme clusters (user-perceived characters) rather than unicode code points, lengths in abstract edit scripts then count grapheme clusters")
+            )
+        .arg(Arg::with_name("normalize")
+            .long("normalize")
+            .short("n")
+            .takes_value(true)
+            .possible_values(&["nfc", "nfd", "nfkc", "nfkd"])
+            .help("Apply this unicode normalization form to the input (and to the edit scripts when applying) first, so strings in different normalization forms produce and accept the same edit scripts")
+            )
+        .arg(Arg::with_name("case")
+            .long("case")
+            .short("c")
+            .help("Express changes in letter case with case instructions (_[*] lowercases all, ^[#n] uppercases the next n characters, etc) rather than with deletions and insertions")
+            )
+        .arg(Arg::with_name("transpositions")
+            .long("transpositions")
+            .short("t")
+            .help("Express two swapped adjacent characters as a single transposition (%[ab] becomes ba), counting as one edit, rather than as a deletion and an insertion")
+            )
+        .arg(Arg::with_name("costs")
+            .long("costs")
+            .short("C")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Cost table to compute the edit distance with; tab-separated lines with either an operation (insertion, deletion, substitution, transposition, case) and its weight, or a source character, target character and the cost for that specific pair")
+            )
+        .arg(Arg::with_name("classes")
+            .long("classes")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Character class definitions for applying edit scripts that refer to classes ({V}); tab-separated lines with the name of the class and its members (aeiou), separate members by spaces if they consist of multiple characters")
+            )
+        .arg(Arg::with_name("algorithm")
+            .long("algorithm")
+            .short("m")
+            .takes_value(true)
+            .possible_values(&["dissimilar", "myers", "levenshtein"])
+            .default_value("dissimilar")
+            .help("Diff algorithm; dissimilar applies a semantic cleanup for more readable edit scripts, myers yields a minimal number of insertions and deletions, levenshtein a minimal number of insertions, deletions and substitutions")
+            )
+        .arg(Arg::with_name("ties")
+            .long("ties")
+            .takes_value(true)
+            .possible_values(&["start", "end", "grouped"])
+            .help("Policy for choosing between edit scripts of equal cost, e.g. which of two identical letters is deleted: place insertions and deletions as close to the start or end as possible, or group them with other changes")
+            )
         .get_matches();
 
+    let unit = if args.is_present("graphemes") {
+        Unit::Grapheme
+    } else {
+        Unit::Char
+    };
+    let mode = if args.is_present("suffix") {
+        Mode::Suffix
+    } else if args.is_present("prefix") {
+        Mode::Prefix
+    } else if args.is_present("infix") {
+        Mode::Infix
+    } else {
+        Mode::Normal
+    };
+    let normalization = args.value_of("normalize").map(|value| {
+        Normalization::from_str(value).unwrap_or_else(|err| {
+            eprintln!("ERROR: {}", err);
+            std::process::exit(1);
+        })
+    });
+    let algorithm =
+        Algorithm::from_str(args.value_of("algorithm").unwrap()).unwrap_or_else(|err| {
+            eprintln!("ERROR: {}", err);
+            std::process::exit(1);
+        });
+    let tie_breaking = args.value_of("ties").map(|value| {
+        TieBreaking::from_str(value).unwrap_or_else(|err| {
+            eprintln!("ERROR: {}", err);
+            std::process::exit(1);
+        })
+    });
+    let option_matching = args
+        .value_of("matching")
+        .map(|value| {
+            OptionMatching::from_str(value).unwrap_or_else(|err| {
+                eprintln!("ERROR: {}", err);
+                std::process::exit(1);
+            })
+        })
+        .unwrap_or_default();
+    let context = args.value_of("context").map(|value| {
+        value.parse::<usize>().unwrap_or_else(|_| {
+            eprintln!("ERROR: Context must be a number, got {}", value);
+            std::process::exit(1);
+        })
+    });
+    let costs = match args.value_of("costs") {
+        Some(filename) => {
+            let table = std::fs::read_to_string(filename).unwrap_or_else(|err| {
+                eprintln!("ERROR: Unable to read cost table {}: {}", filename, err);
+                std::process::exit(1);
+            });
+            CostModel::from_str(&table).unwrap_or_else(|err| {
+                eprintln!("ERROR: {}", err);
+                std::process::exit(1);
+            })
+        }
+        None => CostModel::default(),
+    };
+    let classes = match args.value_of("classes") {
+        Some(filename) => {
+            let definitions = std::fs::read_to_string(filename).unwrap_or_else(|err| {
+                eprintln!(
+                    "ERROR: Unable to read class definitions {}: {}",
+                    filename, err
+                );
+                std::process::exit(1);
+            });
+            CharClasses::from_str(&definitions).unwrap_or_else(|err| {
+                eprintln!("ERROR: {}", err);
+                std::process::exit(1);
+            })
+        }
+        None => CharClasses::default(),
+    };
+    let diffoptions = DiffOptions {
+        mode,
+        generic: args.is_present("abstract"),
+        allow_substitutions: !args.is_present("nosubstitutions"),
+        unit,
+        normalization,
+        factor_case: args.is_present("case"),
+        allow_transpositions: args.is_present("transpositions"),
+        costs,
+        algorithm,
+        tie_breaking,
+        context,
+        wildcards: args.is_present("wildcards"),
+        captures: args.is_present("captures"),
+    };
+    let applyoptions = ApplyOptions {
+        mode: Some(mode),
+        unit,
+        normalization,
+        classes,
+        option_matching,
+    };
+
+    let mut trace: Vec<TraceStep> = Vec::new();
     let stdin = std::io::stdin();
     for (i, line) in stdin.lock().lines().enumerate() {
         if let Ok(line) = line {
@@ -67,51 +265,75 @@ fn main() {
             if line.trim().is_empty() {
                 println!();
             } else if fields.len() >= 2 {
-                let mode = if args.is_present("suffix") {
-                    Mode::Suffix
-                } else if args.is_present("prefix") {
-                    Mode::Prefix
-                } else if args.is_present("infix") {
-                    Mode::Infix
-                } else {
-                    Mode::Normal
-                };
                 print!("{}\t{}\t", fields[0], fields[1]);
-                if args.is_present("apply") {
-                    match EditScript::<String>::from_str(&fields[1]) {
-                        Ok(editscript) => match editscript.apply_to(&fields[0], Some(mode)) {
-                            Ok(result) => print!("\t{}", result),
-                            Err(err) => eprintln!("ERROR: {:?}", err),
-                        },
-                        Err(err) => eprintln!("ERROR: {:?}", err),
+                if args.is_present("compose") {
+                    let composed = EditScript::<String>::from_str(fields[0])
+                        .map_err(|err| parse_error_message(fields[0], &err))
+                        .and_then(|first| {
+                            EditScript::<String>::from_str(fields[1])
+                                .map(|second| (first, second))
+                                .map_err(|err| parse_error_message(fields[1], &err))
+                        })
+                        .and_then(|(mut first, mut second)| {
+                            first.mode = mode;
+                            second.mode = mode;
+                            first
+                                .compose_with(&second, unit)
+                                .map_err(|err| err.to_string())
+                        });
+                    match composed {
+                        Ok(editscript) => print!("\t{}", editscript),
+                        Err(err) => eprintln!("ERROR: {}", err),
                     }
-                } else {
-                    if mode == Mode::Suffix {
-                        let editscript = shortest_edit_script_suffix(
-                            &fields[0],
-                            &fields[1],
-                            args.is_present("abstract"),
-                            !args.is_present("nosubstitutions"),
-                        );
-                        print!("\t{}\t{}", editscript, editscript.distance);
-                    } else {
-                        let editscript = shortest_edit_script(
-                            &fields[0],
-                            &fields[1],
-                            args.is_present("prefix"),
-                            args.is_present("abstract"),
-                            !args.is_present("nosubstitutions"),
-                        );
-                        print!("\t{}\t{}", editscript, editscript.distance);
+                } else if args.is_present("apply") || args.is_present("invert") {
+                    let editscript = EditScript::<String>::from_str(fields[1])
+                        .map_err(|err| parse_error_message(fields[1], &err))
+                        .and_then(|editscript| {
+                            if args.is_present("invert") {
+                                editscript.invert().map_err(|err| err.to_string())
+                            } else {
+                                Ok(editscript)
+                            }
+                        });
+                    match editscript {
+                        Ok(editscript) if args.is_present("apply") && args.is_present("trace") => {
+                            match editscript.apply_traced(fields[0], &applyoptions) {
+                                Ok((result, steps)) => {
+                                    print!("\t{}", result);
+                                    trace = steps;
+                                }
+                                Err(err) => eprintln!("ERROR: {}", err),
+                            }
+                        }
+                        Ok(editscript) if args.is_present("apply") && args.is_present("all") => {
+                            match editscript.apply_all(fields[0], &applyoptions) {
+                                Ok(results) => print!("\t{}", results.join("|")),
+                                Err(err) => eprintln!("ERROR: {}", err),
+                            }
+                        }
+                        Ok(editscript) if args.is_present("apply") => {
+                            match editscript.apply_with(fields[0], &applyoptions) {
+                                Ok(result) => print!("\t{}", result),
+                                Err(err) => eprintln!("ERROR: {}", err),
+                            }
+                        }
+                        Ok(editscript) => print!("\t{}", editscript),
+                        Err(err) => eprintln!("ERROR: {}", err),
                     }
+                } else {
+                    let editscript = shortest_edit_script_with(fields[0], fields[1], &diffoptions);
+                    print!("\t{}\t{}", editscript, editscript.distance);
                 }
                 if fields.len() >= 2 {
                     //retain the rest of the input columns as well
-                    for j in 2..fields.len() {
-                        print!("\t{}", fields[j]);
+                    for field in fields.iter().skip(2) {
+                        print!("\t{}", field);
                     }
                 }
                 println!();
+                for step in trace.drain(..) {
+                    println!("    {}", step);
+                }
             } else {
                 eprintln!(
                     "Unable to process line {}, expected two tab-separated columns",
@@ -0,0 +1,145 @@
+//! Canonicalisation of edit scripts, so edit scripts that do the same have the same form.
+
+use crate::{EditInstruction, EditScript, Mode, Unit};
+
+impl<T: AsRef<str>> EditScript<T> {
+    /// Returns the canonical form of the edit script, see [`EditScript::canonicalize_with()`]
+    pub fn canonicalize(&self) -> EditScript<String> {
+        self.canonicalize_with(Unit::Char)
+    }
+
+    /// Returns the canonical form of the edit script, for edit scripts on the given unit. In the
+    /// canonical form:
+    ///
+    /// * instructions with an empty payload (or length) are removed
+    /// * adjacent identities are merged, as are adjacent wildcards
+    /// * all deletions, insertions, substitutions and transpositions between two other
+    ///   instructions are merged into a single change: a transposition if two units are swapped,
+    ///   otherwise a substitution if as many units are deleted as inserted, otherwise a deletion
+    ///   followed by an insertion
+    /// * repeated case transformations of the entire remainder are removed
+    ///
+    /// The mode and distance are retained.
+    pub fn canonicalize_with(&self, unit: Unit) -> EditScript<String> {
+        let reversed = self.mode == Mode::Suffix;
+        let mut instructions: Vec<EditInstruction<String>> = Vec::new();
+        //changes in the order in which they are applied
+        let mut deleted: Vec<String> = Vec::new();
+        let mut inserted: Vec<String> = Vec::new();
+        for instruction in self.instructions.iter() {
+            match instruction {
+                EditInstruction::Deletion(s) => {
+                    deleted.push(s.as_ref().to_string());
+                    continue;
+                }
+                EditInstruction::Insertion(s) => {
+                    inserted.push(s.as_ref().to_string());
+                    continue;
+                }
+                EditInstruction::Substitution(s, t) => {
+                    deleted.push(s.as_ref().to_string());
+                    inserted.push(t.as_ref().to_string());
+                    continue;
+                }
+                EditInstruction::Transposition(s) => {
+                    let units = unit.split(s.as_ref());
+                    if let [first, second] = units.as_slice() {
+                        deleted.push(s.as_ref().to_string());
+                        inserted.push(second.to_string() + first);
+                        continue;
+                    }
+                }
+                //instructions that do nothing do not separate changes either
+                EditInstruction::Identity(s) if s.as_ref().is_empty() => continue,
+                EditInstruction::GenericIdentity(0)
+                | EditInstruction::LowercaseSpan(0)
+                | EditInstruction::UppercaseSpan(0) => continue,
+                _ => {}
+            }
+            push_changes(
+                &mut instructions,
+                &mut deleted,
+                &mut inserted,
+                reversed,
+                unit,
+            );
+            match (instruction, instructions.last_mut()) {
+                (EditInstruction::Identity(s), Some(EditInstruction::Identity(previous))) => {
+                    if reversed {
+                        previous.insert_str(0, s.as_ref());
+                    } else {
+                        previous.push_str(s.as_ref());
+                    }
+                }
+                (
+                    EditInstruction::GenericIdentity(n),
+                    Some(EditInstruction::GenericIdentity(previous)),
+                ) => *previous += n,
+                (EditInstruction::WildcardIdentity, Some(EditInstruction::WildcardIdentity)) => {}
+                (EditInstruction::LowercaseAll, Some(EditInstruction::LowercaseAll))
+                | (EditInstruction::UppercaseAll, Some(EditInstruction::UppercaseAll)) => {}
+                (instruction, _) => {
+                    instructions.push(instruction.map(|s| s.as_ref().to_string()));
+                }
+            }
+        }
+        push_changes(
+            &mut instructions,
+            &mut deleted,
+            &mut inserted,
+            reversed,
+            unit,
+        );
+        EditScript {
+            mode: self.mode,
+            distance: self.distance,
+            instructions,
+        }
+    }
+
+    /// Tests whether two edit scripts do the same, i.e. whether they have the same mode and the
+    /// same canonical form
+    pub fn semantically_eq<U: AsRef<str>>(&self, other: &EditScript<U>) -> bool {
+        self.mode == other.mode
+            && self.canonicalize().instructions == other.canonicalize().instructions
+    }
+}
+
+/// Adds the collected deletions and insertions (in the order in which they are applied) as a
+/// single change
+pub(crate) fn push_changes(
+    instructions: &mut Vec<EditInstruction<String>>,
+    deleted: &mut Vec<String>,
+    inserted: &mut Vec<String>,
+    reversed: bool,
+    unit: Unit,
+) {
+    let join = |parts: &mut Vec<String>| -> String {
+        if reversed {
+            parts.reverse();
+        }
+        parts.drain(..).collect()
+    };
+    let deleted = join(deleted);
+    let inserted = join(inserted);
+    if !deleted.is_empty() && !inserted.is_empty() {
+        let deletedunits = unit.split(&deleted);
+        let insertedunits = unit.split(&inserted);
+        if let ([a, b], [c, d]) = (deletedunits.as_slice(), insertedunits.as_slice()) {
+            if a == d && b == c && a != b {
+                instructions.push(EditInstruction::Transposition(deleted));
+                return;
+            }
+        }
+        if deletedunits.len() == insertedunits.len() {
+            instructions.push(EditInstruction::Substitution(deleted, inserted));
+            return;
+        }
+    }
+    if !deleted.is_empty() {
+        instructions.push(EditInstruction::Deletion(deleted));
+    }
+    if !inserted.is_empty() {
+        instructions.push(EditInstruction::Insertion(inserted));
+    }
+}
@@ -0,0 +1,97 @@
+//! Named character classes, which edit scripts can refer to (`=[{V}]`, `-[{V}]`).
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::ParseError;
+
+/// A set of named character classes, used when applying edit scripts that refer to them
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct CharClasses {
+    classes: HashMap<String, Vec<String>>,
+}
+
+impl CharClasses {
+    /// Creates an empty set of character classes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines (or redefines) a class. If the members are separated by whitespace, every member
+    /// may consist of multiple characters (e.g. `ch ll rr`), otherwise every grapheme cluster is a
+    /// member by itself (e.g. `aeiou`).
+    pub fn define(&mut self, name: &str, members: &str) {
+        let members: Vec<String> = if members.trim().contains(char::is_whitespace) {
+            members.split_whitespace().map(|s| s.to_string()).collect()
+        } else {
+            members
+                .trim()
+                .graphemes(true)
+                .map(|s| s.to_string())
+                .collect()
+        };
+        self.classes.insert(name.to_string(), members);
+    }
+
+    /// Returns the members of the class, if it is defined
+    pub fn get(&self, name: &str) -> Option<&[String]> {
+        self.classes.get(name).map(|members| members.as_slice())
+    }
+
+    /// Tests whether the string is a member of the class, returns `None` if the class is not
+    /// defined
+    pub fn contains(&self, name: &str, s: &str) -> Option<bool> {
+        self.get(name)
+            .map(|members| members.iter().any(|member| member == s))
+    }
+}
+
+impl FromStr for CharClasses {
+    type Err = ParseError;
+
+    /// Reads class definitions: one class per line, with its name and its members in two
+    /// tab-separated columns (see [`CharClasses::define()`]). Empty lines and lines starting with
+    /// `#` are ignored.
+    fn from_str(table: &str) -> Result<Self, Self::Err> {
+        let mut classes = CharClasses::new();
+        for (i, line) in table.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once('\t') {
+                Some((name, members)) if is_class_name(name.trim()) => {
+                    classes.define(name.trim(), members)
+                }
+                Some((name, _)) => {
+                    return Err(ParseError::WithMessage(format!(
+                        "Invalid class name on line {} of class definitions: {}",
+                        i + 1,
+                        name
+                    )))
+                }
+                None => {
+                    return Err(ParseError::WithMessage(format!(
+                        "Expected two tab-separated columns on line {} of class definitions",
+                        i + 1
+                    )))
+                }
+            }
+        }
+        Ok(classes)
+    }
+}
+
+/// Class names must be non-empty and may not contain any characters with a meaning in the edit
+/// script syntax
+pub(crate) fn is_class_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['{', '}', '[', ']', '|', '>', '\\'])
+}
+
+/// Returns the class name if the payload of an instruction refers to a class (`{name}`)
+pub(crate) fn class_reference(s: &str) -> Option<&str> {
+    s.strip_prefix('{')
+        .and_then(|s| s.strip_suffix('}'))
+        .filter(|name| is_class_name(name))
+}
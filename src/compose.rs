@@ -0,0 +1,242 @@
+//! Composition of edit scripts, an edit script from A to B and one from B to C are combined into a
+//! single edit script from A to C.
+
+use crate::canonical::push_changes;
+use crate::{ComposeError, CostModel, EditInstruction, EditScript, Mode, Unit};
+
+/// A single step of an edit script, on one unit
+#[derive(Clone, Debug, PartialEq)]
+enum Step {
+    /// Consumes the unit and outputs it again
+    Keep(String),
+    /// Consumes any unit and outputs it again
+    KeepAny,
+    /// Consumes the unit
+    Delete(String),
+    /// Consumes one of the options (only in the first edit script)
+    DeleteOptions(Vec<String>),
+    /// Outputs the unit
+    Insert(String),
+}
+
+impl<T: AsRef<str>> EditScript<T> {
+    /// Composes this edit script (from A to B) with another one (from B to C) into a single edit
+    /// script from A to C. Both edit scripts must have the same mode, and the output of this edit
+    /// script must match what the other one expects.
+    pub fn compose(&self, other: &EditScript<T>) -> Result<EditScript<String>, ComposeError> {
+        self.compose_with(other, Unit::Char)
+    }
+
+    /// Composes this edit script with another one like [`EditScript::compose()`], for edit scripts
+    /// that were computed on the given unit
+    pub fn compose_with(
+        &self,
+        other: &EditScript<T>,
+        unit: Unit,
+    ) -> Result<EditScript<String>, ComposeError> {
+        if self.mode != other.mode {
+            return Err(ComposeError(format!(
+                "Unable to compose edit scripts with different modes ({:?} and {:?})",
+                self.mode, other.mode
+            )));
+        }
+        if self.mode == Mode::Infix {
+            return Err(ComposeError(
+                "Unable to compose edit scripts in infix mode".to_string(),
+            ));
+        }
+        let reversed = self.mode == Mode::Suffix;
+        let first = steps(&self.instructions, unit, reversed, true)?;
+        let second = steps(&other.instructions, unit, reversed, false)?;
+        //in prefix and suffix mode, the part of the input that an edit script does not cover is
+        //kept as it is
+        let passthrough = self.mode != Mode::Normal;
+
+        let mut result: Vec<Step> = Vec::new();
+        let mut first = first.into_iter().peekable();
+        let mut second = second.into_iter().peekable();
+        loop {
+            //insertions of the second edit script and deletions of the first one do not depend on
+            //the other edit script
+            if let Some(Step::Insert(_)) = second.peek() {
+                result.push(second.next().expect("peeked"));
+                continue;
+            }
+            if let Some(Step::Delete(_)) | Some(Step::DeleteOptions(_)) = first.peek() {
+                result.push(first.next().expect("peeked"));
+                continue;
+            }
+            //the unit output by the first edit script is consumed by the second one
+            let step = match (first.next(), second.next()) {
+                (None, None) => break,
+                (Some(step), None) if passthrough => step,
+                //the second edit script ignores the remainder of the output of the first one, but
+                //the input that the first edit script expects is still required
+                (Some(Step::Keep(s)), None) => Step::Delete(s),
+                (Some(Step::Insert(_)), None) => continue,
+                (Some(Step::KeepAny), None) => {
+                    return Err(ComposeError(
+                        "Unable to compose edit scripts, the second edit script ignores a generic identity of the first one"
+                            .to_string(),
+                    ));
+                }
+                (None, Some(step)) => {
+                    if passthrough {
+                        step
+                    } else {
+                        return Err(ComposeError(
+                            "Unable to compose edit scripts, the second edit script expects more than the first one outputs"
+                                .to_string(),
+                        ));
+                    }
+                }
+                (Some(Step::Insert(s)), Some(Step::Delete(t))) => {
+                    expect_equal(&s, &t)?;
+                    continue;
+                }
+                (Some(Step::Insert(s)), Some(Step::Keep(t))) => {
+                    expect_equal(&s, &t)?;
+                    Step::Insert(s)
+                }
+                (Some(Step::Insert(s)), Some(Step::KeepAny)) => Step::Insert(s),
+                (Some(Step::Keep(s)), Some(Step::Delete(t))) => {
+                    expect_equal(&s, &t)?;
+                    Step::Delete(s)
+                }
+                (Some(Step::Keep(s)), Some(Step::Keep(t))) => {
+                    expect_equal(&s, &t)?;
+                    Step::Keep(s)
+                }
+                (Some(Step::Keep(s)), Some(Step::KeepAny)) => Step::Keep(s),
+                (Some(Step::KeepAny), Some(step)) => step,
+                (Some(_), _) => unreachable!("handled before"),
+            };
+            result.push(step);
+        }
+
+        let instructions = to_instructions(result, reversed, unit);
+        Ok(EditScript {
+            mode: self.mode,
+            distance: CostModel::default().distance(&instructions, unit),
+            instructions,
+        })
+    }
+}
+
+fn expect_equal(s: &str, t: &str) -> Result<(), ComposeError> {
+    if s == t {
+        Ok(())
+    } else {
+        Err(ComposeError(format!(
+            "Unable to compose edit scripts, the first edit script outputs '{}' where the second one expects '{}'",
+            s, t
+        )))
+    }
+}
+
+/// Expands the instructions to steps on single units, in the order in which they are applied
+fn steps<T: AsRef<str>>(
+    instructions: &[EditInstruction<T>],
+    unit: Unit,
+    reversed: bool,
+    first: bool,
+) -> Result<Vec<Step>, ComposeError> {
+    let mut steps = Vec::new();
+    let units = |s: &str| -> Vec<String> {
+        let mut units: Vec<String> = unit.split(s).into_iter().map(|u| u.to_string()).collect();
+        if reversed {
+            units.reverse();
+        }
+        units
+    };
+    for instruction in instructions {
+        match instruction {
+            EditInstruction::Identity(s) => {
+                steps.extend(units(s.as_ref()).into_iter().map(Step::Keep))
+            }
+            EditInstruction::GenericIdentity(n) => {
+                steps.extend((0..*n).map(|_| Step::KeepAny));
+            }
+            EditInstruction::Deletion(s) => {
+                steps.extend(units(s.as_ref()).into_iter().map(Step::Delete))
+            }
+            EditInstruction::Insertion(s) => {
+                steps.extend(units(s.as_ref()).into_iter().map(Step::Insert))
+            }
+            EditInstruction::Substitution(s, t) => {
+                steps.extend(units(s.as_ref()).into_iter().map(Step::Delete));
+                steps.extend(units(t.as_ref()).into_iter().map(Step::Insert));
+            }
+            EditInstruction::Transposition(s) => {
+                let transposed = units(s.as_ref());
+                if transposed.len() != 2 {
+                    return Err(ComposeError(format!(
+                        "A transposition takes exactly two units, got '{}'",
+                        s.as_ref()
+                    )));
+                }
+                steps.extend(transposed.iter().cloned().map(Step::Delete));
+                steps.extend(transposed.into_iter().rev().map(Step::Insert));
+            }
+            EditInstruction::DeletionOptions(v) if first => {
+                steps.push(Step::DeleteOptions(
+                    v.iter().map(|s| s.as_ref().to_string()).collect(),
+                ));
+            }
+            _ => {
+                return Err(ComposeError(format!(
+                    "Unable to compose edit scripts with instruction {}",
+                    instruction.map(|s| s.as_ref().to_string())
+                )))
+            }
+        }
+    }
+    Ok(steps)
+}
+
+/// Groups the steps into edit instructions. Between two identities, all deletions and insertions
+/// are grouped into a single change like in [`EditScript::canonicalize()`].
+fn to_instructions(steps: Vec<Step>, reversed: bool, unit: Unit) -> Vec<EditInstruction<String>> {
+    let mut instructions = Vec::new();
+    let mut deleted: Vec<String> = Vec::new();
+    let mut inserted: Vec<String> = Vec::new();
+    for step in steps {
+        match step {
+            Step::Delete(s) => deleted.push(s),
+            Step::Insert(s) => inserted.push(s),
+            step => {
+                push_changes(
+                    &mut instructions,
+                    &mut deleted,
+                    &mut inserted,
+                    reversed,
+                    unit,
+                );
+                match (step, instructions.last_mut()) {
+                    (Step::Keep(s), Some(EditInstruction::Identity(previous))) => {
+                        if reversed {
+                            previous.insert_str(0, &s);
+                        } else {
+                            previous.push_str(&s);
+                        }
+                    }
+                    (Step::Keep(s), _) => instructions.push(EditInstruction::Identity(s)),
+                    (Step::KeepAny, Some(EditInstruction::GenericIdentity(n))) => *n += 1,
+                    (Step::KeepAny, _) => instructions.push(EditInstruction::GenericIdentity(1)),
+                    (Step::DeleteOptions(options), _) => {
+                        instructions.push(EditInstruction::DeletionOptions(options))
+                    }
+                    (Step::Delete(_), _) | (Step::Insert(_), _) => unreachable!("handled before"),
+                }
+            }
+        }
+    }
+    push_changes(
+        &mut instructions,
+        &mut deleted,
+        &mut inserted,
+        reversed,
+        unit,
+    );
+    instructions
+}
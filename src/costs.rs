@@ -0,0 +1,189 @@
+//! Weighted costs for the edit operations, these determine the distance of an edit script.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::{EditInstruction, ParseError, Unit};
+
+/// The costs of the various edit operations, used to compute the distance of an edit script.
+/// The default model assigns a cost of one to every operation on a single unit, an insertion or
+/// deletion of n units costs n.
+///
+/// In addition to the weights per operation, costs may be set for specific pairs of units, e.g. a
+/// low cost for substituting a character by the same character without diacritic. A pair with an
+/// empty source applies to the insertion of the target unit, a pair with an empty target to the
+/// deletion of the source unit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CostModel {
+    /// Cost of inserting a unit
+    pub insertion: u32,
+
+    /// Cost of deleting a unit
+    pub deletion: u32,
+
+    /// Cost of substituting a unit by another
+    pub substitution: u32,
+
+    /// Cost of swapping two adjacent units
+    pub transposition: u32,
+
+    /// Cost of a single case instruction
+    pub case: u32,
+
+    /// Costs for specific pairs of units, these take precedence over the weights above
+    pairs: HashMap<(String, String), u32>,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            insertion: 1,
+            deletion: 1,
+            substitution: 1,
+            transposition: 1,
+            case: 1,
+            pairs: HashMap::new(),
+        }
+    }
+}
+
+impl CostModel {
+    /// Sets the cost of substituting `source` by `target`. Use an empty source or target to set the
+    /// cost of inserting or deleting a specific unit.
+    pub fn set_pair_cost(&mut self, source: &str, target: &str, cost: u32) {
+        self.pairs
+            .insert((source.to_string(), target.to_string()), cost);
+    }
+
+    /// Returns the cost that was set for this specific pair, if any
+    pub fn pair_cost(&self, source: &str, target: &str) -> Option<u32> {
+        self.pairs
+            .get(&(source.to_string(), target.to_string()))
+            .copied()
+    }
+
+    /// Returns the cost of inserting the text
+    pub fn insertion_cost(&self, s: &str, unit: Unit) -> u32 {
+        unit.split(s)
+            .into_iter()
+            .map(|t| self.pair_cost("", t).unwrap_or(self.insertion))
+            .sum()
+    }
+
+    /// Returns the cost of deleting the text
+    pub fn deletion_cost(&self, s: &str, unit: Unit) -> u32 {
+        unit.split(s)
+            .into_iter()
+            .map(|s| self.pair_cost(s, "").unwrap_or(self.deletion))
+            .sum()
+    }
+
+    /// Returns the cost of substituting the first text by the second one, unit by unit
+    pub fn substitution_cost(&self, s: &str, t: &str, unit: Unit) -> u32 {
+        unit.split(s)
+            .into_iter()
+            .zip(unit.split(t))
+            .map(|(s, t)| {
+                if s == t {
+                    0
+                } else {
+                    self.pair_cost(s, t).unwrap_or(self.substitution)
+                }
+            })
+            .sum()
+    }
+
+    /// Returns the cost of a single edit instruction
+    pub fn cost<T: AsRef<str>>(&self, instruction: &EditInstruction<T>, unit: Unit) -> u32 {
+        match instruction {
+            EditInstruction::Insertion(s) => self.insertion_cost(s.as_ref(), unit),
+            EditInstruction::Deletion(s) => self.deletion_cost(s.as_ref(), unit),
+            EditInstruction::InsertionOptions(v) => v
+                .iter()
+                .map(|s| self.insertion_cost(s.as_ref(), unit))
+                .min()
+                .unwrap_or(0),
+            EditInstruction::DeletionOptions(v) => v
+                .iter()
+                .map(|s| self.deletion_cost(s.as_ref(), unit))
+                .min()
+                .unwrap_or(0),
+            EditInstruction::Substitution(s, t) => {
+                self.substitution_cost(s.as_ref(), t.as_ref(), unit)
+            }
+            EditInstruction::DeletionClass(_) => self.deletion,
+            //copying captured material counts as a single insertion
+            EditInstruction::BackReference(_) => self.insertion,
+            EditInstruction::Captured(_, instruction) => self.cost(instruction, unit),
+            EditInstruction::Transposition(_) => self.transposition,
+            EditInstruction::LowercaseAll
+            | EditInstruction::UppercaseAll
+            | EditInstruction::LowercaseSpan(_)
+            | EditInstruction::UppercaseSpan(_) => self.case,
+            EditInstruction::Identity(_)
+            | EditInstruction::GenericIdentity(_)
+            | EditInstruction::WildcardIdentity
+            | EditInstruction::IdentityClass(_)
+            | EditInstruction::IdentityOptions(_) => 0,
+        }
+    }
+
+    /// Returns the total cost of a sequence of edit instructions
+    pub fn distance<T: AsRef<str>>(&self, instructions: &[EditInstruction<T>], unit: Unit) -> u32 {
+        instructions
+            .iter()
+            .map(|instruction| self.cost(instruction, unit))
+            .sum()
+    }
+}
+
+impl FromStr for CostModel {
+    type Err = ParseError;
+
+    /// Reads a cost table: one entry per line with tab-separated columns. Lines with two columns
+    /// set the weight of an operation (`insertion`, `deletion`, `substitution`, `transposition`
+    /// or `case`), lines with three columns set the cost for a specific pair of units (source,
+    /// target, cost). Empty lines and lines starting with `#` are ignored.
+    fn from_str(table: &str) -> Result<Self, Self::Err> {
+        let mut costmodel = CostModel::default();
+        for (i, line) in table.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let cost = fields
+                .last()
+                .and_then(|cost| cost.trim().parse::<u32>().ok())
+                .ok_or_else(|| {
+                    ParseError::WithMessage(format!("Invalid cost on line {} of cost table", i + 1))
+                })?;
+            match fields.as_slice() {
+                [operation, _] => {
+                    let weight = match operation.trim() {
+                        "insertion" => &mut costmodel.insertion,
+                        "deletion" => &mut costmodel.deletion,
+                        "substitution" => &mut costmodel.substitution,
+                        "transposition" => &mut costmodel.transposition,
+                        "case" => &mut costmodel.case,
+                        operation => {
+                            return Err(ParseError::WithMessage(format!(
+                                "Unknown operation on line {} of cost table: {}",
+                                i + 1,
+                                operation
+                            )))
+                        }
+                    };
+                    *weight = cost;
+                }
+                [source, target, _] => costmodel.set_pair_cost(source, target, cost),
+                _ => {
+                    return Err(ParseError::WithMessage(format!(
+                        "Expected two or three tab-separated columns on line {} of cost table",
+                        i + 1
+                    )))
+                }
+            }
+        }
+        Ok(costmodel)
+    }
+}
@@ -0,0 +1,189 @@
+//! Computation of the edit distance only, without building an edit script.
+
+use crate::{Algorithm, CostModel, DiffOp, EditInstruction, Unit};
+
+/// Computes the edit distance between two strings without building an edit script. It equals the
+/// distance of the edit script computed by [`crate::shortest_edit_script()`] (or with the default
+/// [`crate::DiffOptions`]): a deletion directly followed by an insertion of the same length is a
+/// substitution, which counts once per differing character.
+pub fn levenshtein(a: &str, b: &str) -> u32 {
+    levenshtein_bounded(a, b, u32::MAX).expect("unbounded distance always exists")
+}
+
+/// Computes the edit distance between two strings like [`levenshtein()`], but gives up as soon as
+/// the distance is known to exceed `max`, in which case `None` is returned.
+pub fn levenshtein_bounded(a: &str, b: &str, max: u32) -> Option<u32> {
+    //the Levenshtein distance never exceeds the distance of the edit script, so it rules out most
+    //pairs before they are aligned
+    if distance(a, b, max)? == 0 {
+        return Some(0);
+    }
+    let distance = script_distance(a, b);
+    if distance > max {
+        None
+    } else {
+        Some(distance)
+    }
+}
+
+/// Sums the costs of the changes in the default alignment, pairing deletions and insertions into
+/// substitutions like the edit script does
+fn script_distance(a: &str, b: &str) -> u32 {
+    let costs = CostModel::default();
+    let mut distance = 0;
+    //the last change if it directly precedes the current operation
+    let mut previous: Option<EditInstruction<&str>> = None;
+    for op in Algorithm::default().backend().diff(a, b) {
+        let instruction = match (op, previous.take()) {
+            (DiffOp::Equal(_, _), _) => continue,
+            (DiffOp::Delete(s), Some(EditInstruction::Insertion(t)))
+                if Unit::Char.count(&a[s.clone()]) == Unit::Char.count(t) =>
+            {
+                distance -= costs.cost(&EditInstruction::Insertion(t), Unit::Char);
+                distance += costs.cost(&EditInstruction::Substitution(&a[s], t), Unit::Char);
+                continue;
+            }
+            (DiffOp::Insert(t), Some(EditInstruction::Deletion(s)))
+                if Unit::Char.count(s) == Unit::Char.count(&b[t.clone()]) =>
+            {
+                distance -= costs.cost(&EditInstruction::Deletion(s), Unit::Char);
+                distance += costs.cost(&EditInstruction::Substitution(s, &b[t]), Unit::Char);
+                continue;
+            }
+            (DiffOp::Delete(s), _) => EditInstruction::Deletion(&a[s]),
+            (DiffOp::Insert(t), _) => EditInstruction::Insertion(&b[t]),
+        };
+        distance += costs.cost(&instruction, Unit::Char);
+        previous = Some(instruction);
+    }
+    distance
+}
+
+/// Computes the Levenshtein distance (each substitution counts once), `None` if it exceeds `max`
+fn distance(a: &str, b: &str, max: u32) -> Option<u32> {
+    //common prefixes and suffixes do not contribute to the distance
+    let prefix: usize = a
+        .chars()
+        .zip(b.chars())
+        .take_while(|(x, y)| x == y)
+        .map(|(x, _)| x.len_utf8())
+        .sum();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let suffix: usize = a
+        .chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(x, y)| x == y)
+        .map(|(x, _)| x.len_utf8())
+        .sum();
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+
+    let (alen, blen) = (a.chars().count(), b.chars().count());
+    //the pattern is the shorter of the two strings
+    let (pattern, patternlen, text, textlen) = if alen <= blen {
+        (a, alen, b, blen)
+    } else {
+        (b, blen, a, alen)
+    };
+    if (textlen - patternlen) as u64 > max as u64 {
+        return None;
+    }
+    if patternlen == 0 {
+        return Some(textlen as u32);
+    }
+    if patternlen <= 64 {
+        bitparallel(pattern, patternlen, text, textlen, max)
+    } else {
+        dynamic(pattern, text, max)
+    }
+}
+
+/// Bit-vector algorithm by Myers (1999) in the formulation of Hyyrö (2003), for patterns of at
+/// most 64 characters
+fn bitparallel(
+    pattern: &str,
+    patternlen: usize,
+    text: &str,
+    textlen: usize,
+    max: u32,
+) -> Option<u32> {
+    //match vectors: the bit for every position of the pattern where the character occurs
+    let mut ascii = [0u64; 128];
+    let mut other: Vec<(char, u64)> = Vec::new();
+    for (i, c) in pattern.chars().enumerate() {
+        if c.is_ascii() {
+            ascii[c as usize] |= 1 << i;
+        } else if let Some((_, bits)) = other.iter_mut().find(|(x, _)| *x == c) {
+            *bits |= 1 << i;
+        } else {
+            other.push((c, 1 << i));
+        }
+    }
+
+    let last = 1u64 << (patternlen - 1);
+    let mut pv: u64 = if patternlen == 64 {
+        !0
+    } else {
+        (1 << patternlen) - 1
+    };
+    let mut mv: u64 = 0;
+    let mut score = patternlen as u64;
+    for (j, c) in text.chars().enumerate() {
+        let eq = if c.is_ascii() {
+            ascii[c as usize]
+        } else {
+            other
+                .iter()
+                .find(|(x, _)| *x == c)
+                .map_or(0, |(_, bits)| *bits)
+        };
+        let xv = eq | mv;
+        let xh = ((eq & pv).wrapping_add(pv) ^ pv) | eq;
+        let mut ph = mv | !(xh | pv);
+        let mut mh = pv & xh;
+        if ph & last != 0 {
+            score += 1;
+        } else if mh & last != 0 {
+            score -= 1;
+        }
+        ph = (ph << 1) | 1;
+        mh <<= 1;
+        pv = mh | !(xv | ph);
+        mv = ph & xv;
+        //every remaining character of the text can lower the score by at most one
+        let remaining = (textlen - j - 1) as u64;
+        if score > max as u64 + remaining {
+            return None;
+        }
+    }
+    Some(score as u32)
+}
+
+/// Dynamic programming over two rows, for longer patterns
+fn dynamic(pattern: &str, text: &str, max: u32) -> Option<u32> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let mut row: Vec<u32> = (0..=pattern.len() as u32).collect();
+    for (j, c) in text.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = j as u32 + 1;
+        let mut minimum = row[0];
+        for i in 1..=pattern.len() {
+            let above = row[i];
+            row[i] = (diagonal + (pattern[i - 1] != c) as u32)
+                .min(above + 1)
+                .min(row[i - 1] + 1);
+            diagonal = above;
+            minimum = minimum.min(row[i]);
+        }
+        //the distance never drops below the minimum of a row
+        if minimum > max {
+            return None;
+        }
+    }
+    let score = row[pattern.len()];
+    if score > max {
+        None
+    } else {
+        Some(score)
+    }
+}
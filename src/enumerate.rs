@@ -0,0 +1,208 @@
+//! Enumeration of all outputs an edit script can produce for an input, for edit scripts with
+//! alternatives (options and classes) and for the different ways to match an edit script in infix
+//! mode.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    apply_forward, apply_script, class_members, ApplyError, ApplyOptions, EditInstruction,
+    EditScript, Mode,
+};
+
+/// Applies every variant of the edit script to the input and returns the distinct outputs, sorted
+pub(crate) fn apply_all(
+    editscript: &EditScript<&str>,
+    input: &str,
+    options: &ApplyOptions,
+) -> Result<Vec<String>, ApplyError> {
+    if let Some(normalization) = options.normalization {
+        let editscript = EditScript {
+            mode: editscript.mode,
+            distance: editscript.distance,
+            instructions: editscript
+                .instructions
+                .iter()
+                .map(|instruction| instruction.map(|s| normalization.normalize(s)))
+                .collect(),
+        };
+        let options = ApplyOptions {
+            normalization: None,
+            ..options.clone()
+        };
+        return apply_all(
+            &editscript.as_ref(),
+            &normalization.normalize(input),
+            &options,
+        );
+    }
+    let mode = options.mode.unwrap_or(editscript.mode);
+    let variants = variants(&editscript.instructions, options)?;
+    let mut outputs = BTreeSet::new();
+    let mut error = None;
+    match mode {
+        Mode::Infix if editscript.instructions.is_empty() => return Err(ApplyError::EmptyScript),
+        Mode::Infix if !input.is_empty() => {
+            let mut segmenter = Segmenter {
+                variants: &variants,
+                options,
+                outputs: BTreeSet::new(),
+                error: None,
+            };
+            segmenter.segment(input, String::new(), None, false)?;
+            outputs = segmenter.outputs;
+            error = segmenter.error;
+        }
+        //in infix mode, an empty input only has the one position to attempt
+        _ => {
+            for instructions in variants {
+                let variant = EditScript {
+                    mode,
+                    distance: editscript.distance,
+                    instructions,
+                };
+                match apply_script(&variant, input, options, None) {
+                    Ok(output) => {
+                        outputs.insert(output);
+                    }
+                    Err(err) if err.is_permanent() => return Err(err),
+                    Err(err) => {
+                        error.get_or_insert(err);
+                    }
+                }
+            }
+        }
+    }
+    if outputs.is_empty() {
+        if let Some(err) = error {
+            return Err(err);
+        }
+        //there were no variants at all (an empty class), report what applying reports
+        return apply_script(editscript, input, options, None).map(|output| vec![output]);
+    }
+    Ok(outputs.into_iter().collect())
+}
+
+/// Expands the instructions into all variants without alternatives, where every option and class
+/// is replaced by one of its members
+fn variants<'a>(
+    instructions: &[EditInstruction<&'a str>],
+    options: &'a ApplyOptions,
+) -> Result<Vec<Vec<EditInstruction<&'a str>>>, ApplyError> {
+    let mut variants = vec![Vec::with_capacity(instructions.len())];
+    for (i, instruction) in instructions.iter().enumerate() {
+        let alternatives = alternatives(instruction, options, i)?;
+        variants = variants
+            .into_iter()
+            .flat_map(|variant| {
+                alternatives.iter().map(move |alternative| {
+                    let mut variant = variant.clone();
+                    variant.push(alternative.clone());
+                    variant
+                })
+            })
+            .collect();
+    }
+    Ok(variants)
+}
+
+/// Returns the instructions that the instruction at index `i` may stand for
+fn alternatives<'a>(
+    instruction: &EditInstruction<&'a str>,
+    options: &'a ApplyOptions,
+    i: usize,
+) -> Result<Vec<EditInstruction<&'a str>>, ApplyError> {
+    Ok(match instruction {
+        EditInstruction::InsertionOptions(v) => {
+            v.iter().map(|s| EditInstruction::Insertion(*s)).collect()
+        }
+        EditInstruction::DeletionOptions(v) => {
+            v.iter().map(|s| EditInstruction::Deletion(*s)).collect()
+        }
+        EditInstruction::IdentityOptions(v) => {
+            v.iter().map(|s| EditInstruction::Identity(*s)).collect()
+        }
+        EditInstruction::IdentityClass(name) => class_members(name, options, i)?
+            .iter()
+            .map(|s| EditInstruction::Identity(s.as_str()))
+            .collect(),
+        EditInstruction::DeletionClass(name) => class_members(name, options, i)?
+            .iter()
+            .map(|s| EditInstruction::Deletion(s.as_str()))
+            .collect(),
+        EditInstruction::Captured(name, instruction) => alternatives(instruction, options, i)?
+            .into_iter()
+            .map(|alternative| EditInstruction::Captured(*name, Box::new(alternative)))
+            .collect(),
+        instruction => vec![instruction.clone()],
+    })
+}
+
+/// Enumerates the outputs of applying the variants of an edit script in infix mode
+struct Segmenter<'a, 'b> {
+    variants: &'b [Vec<EditInstruction<&'a str>>],
+    options: &'b ApplyOptions,
+    outputs: BTreeSet<String>,
+    /// The first error, it is at the start of the input if the edit script matches nowhere
+    error: Option<ApplyError>,
+}
+
+impl Segmenter<'_, '_> {
+    /// Enumerates the outputs of all ways to apply the variants to occurrences in the remaining
+    /// input, `output` is the output so far. As in apply_with(), occurrences may not overlap and
+    /// may not be left out, except in favour of an overlapping occurrence: one that starts within
+    /// the next `deadline` bytes.
+    fn segment(
+        &mut self,
+        input: &str,
+        output: String,
+        deadline: Option<usize>,
+        matched: bool,
+    ) -> Result<(), ApplyError> {
+        if deadline == Some(0) {
+            //an occurrence that was left out does not overlap with any other
+            return Ok(());
+        }
+        if input.is_empty() {
+            if deadline.is_none() && matched {
+                self.outputs.insert(output);
+            }
+            return Ok(());
+        }
+        let unit = self.options.unit;
+        //the length of the shortest occurrence here
+        let mut shortest: Option<usize> = None;
+        for instructions in self.variants {
+            match apply_forward(instructions, input, self.options, &mut None) {
+                Err(err) if err.is_permanent() => return Err(err),
+                Err(err) => {
+                    self.error.get_or_insert(err);
+                }
+                Ok((head, consumed, input)) => {
+                    shortest = Some(shortest.map_or(consumed, |shortest| shortest.min(consumed)));
+                    //an occurrence that consumes nothing is followed by one unchanged unit
+                    let next = if consumed > 0 {
+                        consumed
+                    } else {
+                        unit.forward(&input, 0, 1).unwrap_or(input.len())
+                    };
+                    let output = output.clone() + head.as_str() + &input[consumed..next];
+                    self.segment(&input[next..], output, None, true)?;
+                }
+            }
+        }
+        //leave out the occurrence(s) here
+        let deadline = match (shortest, deadline) {
+            (Some(0), _) => return Ok(()),
+            (Some(length), Some(deadline)) => Some(length.min(deadline)),
+            (Some(length), None) => Some(length),
+            (None, deadline) => deadline,
+        };
+        let next = unit.forward(input, 0, 1).unwrap_or(input.len());
+        self.segment(
+            &input[next..],
+            output + &input[..next],
+            deadline.map(|deadline| deadline.saturating_sub(next)),
+            matched,
+        )
+    }
+}
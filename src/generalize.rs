@@ -0,0 +1,156 @@
+//! Generalisation of sets of edit scripts into edit scripts with options (disjunctions).
+
+use std::collections::HashMap;
+
+use crate::{EditInstruction, EditScript, Mode, Unit};
+
+/// An edit script that results from merging edit scripts with [`generalize()`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generalization {
+    /// The merged edit script
+    pub script: EditScript<String>,
+    /// The indices of the original edit scripts that were merged into this one
+    pub covers: Vec<usize>,
+}
+
+/// A position in the structure of an edit script
+#[derive(Debug, Clone, PartialEq)]
+enum Slot {
+    /// An identity check, with one or more options
+    Identity(Vec<String>),
+    /// A deletion, with one or more options
+    Deletion(Vec<String>),
+    /// Any other instruction, which has to be the same in all merged edit scripts
+    Fixed(EditInstruction<String>),
+}
+
+/// Merges edit scripts that share a structure into edit scripts with options, see
+/// [`generalize_with()`]
+pub fn generalize<T: AsRef<str>>(scripts: &[EditScript<T>]) -> Vec<Generalization> {
+    generalize_with(scripts, Unit::Char)
+}
+
+/// Merges edit scripts that share a structure into edit scripts with options, for edit scripts on
+/// the given unit. Edit scripts share a structure if they have the same mode and their canonical
+/// forms (see [`EditScript::canonicalize()`]) only differ in what identities check and what
+/// deletions remove; substitutions count as a deletion followed by an insertion. These become
+/// identity options (`=[a|b]`) and deletion options (`-[a|b]`) in the merged edit script, longer
+/// options come first so they are tried first. Insertions, abstract identities, transpositions and
+/// case transformations have to be the same.
+///
+/// Returns one merged edit script per structure, in order of first occurrence, along with the
+/// indices of the edit scripts it covers. Its distance is the maximum distance of these.
+pub fn generalize_with<T: AsRef<str>>(
+    scripts: &[EditScript<T>],
+    unit: Unit,
+) -> Vec<Generalization> {
+    let mut groups: Vec<(Vec<Slot>, Generalization)> = Vec::new();
+    let mut index: HashMap<(Mode, Vec<String>), usize> = HashMap::new();
+    for (i, script) in scripts.iter().enumerate() {
+        let slots = slots(&script.canonicalize_with(unit));
+        let key = (script.mode, signature(&slots));
+        match index.get(&key) {
+            Some(&groupindex) => {
+                let (merged, generalization) = &mut groups[groupindex];
+                for (slot, other) in merged.iter_mut().zip(slots) {
+                    match (slot, other) {
+                        (Slot::Identity(options), Slot::Identity(others))
+                        | (Slot::Deletion(options), Slot::Deletion(others)) => {
+                            for option in others {
+                                if !options.contains(&option) {
+                                    options.push(option);
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                generalization.covers.push(i);
+                generalization.script.distance =
+                    generalization.script.distance.max(script.distance);
+            }
+            None => {
+                index.insert(key, groups.len());
+                groups.push((
+                    slots,
+                    Generalization {
+                        script: EditScript {
+                            mode: script.mode,
+                            distance: script.distance,
+                            instructions: Vec::new(),
+                        },
+                        covers: vec![i],
+                    },
+                ));
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(slots, generalization)| {
+            let script = EditScript {
+                mode: generalization.script.mode,
+                distance: generalization.script.distance,
+                instructions: slots
+                    .into_iter()
+                    .map(|slot| to_instruction(slot, unit))
+                    .collect(),
+            };
+            Generalization {
+                //deletions and insertions without options become substitutions again
+                script: script.canonicalize_with(unit),
+                covers: generalization.covers,
+            }
+        })
+        .collect()
+}
+
+/// Splits a canonical edit script into slots
+fn slots(script: &EditScript<String>) -> Vec<Slot> {
+    let mut slots = Vec::new();
+    for instruction in script.instructions.iter() {
+        match instruction {
+            EditInstruction::Identity(s) => slots.push(Slot::Identity(vec![s.clone()])),
+            EditInstruction::IdentityOptions(v) => slots.push(Slot::Identity(v.clone())),
+            EditInstruction::Deletion(s) => slots.push(Slot::Deletion(vec![s.clone()])),
+            EditInstruction::DeletionOptions(v) => slots.push(Slot::Deletion(v.clone())),
+            EditInstruction::Substitution(s, t) => {
+                slots.push(Slot::Deletion(vec![s.clone()]));
+                slots.push(Slot::Fixed(EditInstruction::Insertion(t.clone())));
+            }
+            instruction => slots.push(Slot::Fixed(instruction.clone())),
+        }
+    }
+    slots
+}
+
+/// The structure of the slots, the same for all edit scripts that can be merged
+fn signature(slots: &[Slot]) -> Vec<String> {
+    slots
+        .iter()
+        .map(|slot| match slot {
+            Slot::Identity(_) => "=".to_string(),
+            Slot::Deletion(_) => "-".to_string(),
+            Slot::Fixed(instruction) => instruction.to_string(),
+        })
+        .collect()
+}
+
+fn to_instruction(slot: Slot, unit: Unit) -> EditInstruction<String> {
+    let longest_first = |mut options: Vec<String>| -> Vec<String> {
+        options.sort_by_key(|option| std::cmp::Reverse(unit.split(option).len()));
+        options
+    };
+    match slot {
+        Slot::Identity(mut options) if options.len() == 1 => {
+            EditInstruction::Identity(options.remove(0))
+        }
+        Slot::Identity(options) => EditInstruction::IdentityOptions(longest_first(options)),
+        Slot::Deletion(mut options) if options.len() == 1 => {
+            EditInstruction::Deletion(options.remove(0))
+        }
+        Slot::Deletion(options) => EditInstruction::DeletionOptions(longest_first(options)),
+        Slot::Fixed(instruction) => instruction,
+    }
+}
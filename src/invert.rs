@@ -0,0 +1,86 @@
+//! Inversion of edit scripts, an edit script from A to B becomes an edit script from B to A.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{EditInstruction, EditScript, InvertError};
+
+impl<T: AsRef<str>> EditScript<T> {
+    /// Returns the inverse of the edit script, which transforms the target back to the source.
+    /// The mode is retained. Fails for instructions that lose information and can not be inverted
+    /// unambiguously, such as insertion options and case transformations.
+    pub fn invert(&self) -> Result<EditScript<String>, InvertError> {
+        Ok(EditScript {
+            mode: self.mode,
+            distance: self.distance,
+            instructions: self
+                .instructions
+                .iter()
+                .map(|instruction| instruction.invert())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl<T: AsRef<str>> EditInstruction<T> {
+    /// Returns the inverse of the edit instruction
+    pub fn invert(&self) -> Result<EditInstruction<String>, InvertError> {
+        match self {
+            EditInstruction::Insertion(s) => Ok(EditInstruction::Deletion(s.as_ref().to_string())),
+            EditInstruction::Deletion(s) => Ok(EditInstruction::Insertion(s.as_ref().to_string())),
+            EditInstruction::Identity(s) => Ok(EditInstruction::Identity(s.as_ref().to_string())),
+            EditInstruction::GenericIdentity(n) => Ok(EditInstruction::GenericIdentity(*n)),
+            EditInstruction::WildcardIdentity => Ok(EditInstruction::WildcardIdentity),
+            EditInstruction::IdentityOptions(v) => Ok(EditInstruction::IdentityOptions(
+                v.iter().map(|s| s.as_ref().to_string()).collect(),
+            )),
+            EditInstruction::Substitution(s, t) => Ok(EditInstruction::Substitution(
+                t.as_ref().to_string(),
+                s.as_ref().to_string(),
+            )),
+            EditInstruction::Transposition(s) => {
+                Ok(EditInstruction::Transposition(swap(s.as_ref())?))
+            }
+            EditInstruction::IdentityClass(s) => {
+                Ok(EditInstruction::IdentityClass(s.as_ref().to_string()))
+            }
+            EditInstruction::Captured(name, instruction) => Ok(EditInstruction::Captured(
+                name.as_ref().to_string(),
+                Box::new(instruction.invert()?),
+            )),
+            EditInstruction::BackReference(_) => Err(InvertError(format!(
+                "Unable to invert {}, the captured material is not known",
+                self.map(|s| s.as_ref().to_string())
+            ))),
+            EditInstruction::InsertionOptions(_)
+            | EditInstruction::DeletionOptions(_)
+            | EditInstruction::DeletionClass(_) => Err(InvertError(format!(
+                "Unable to invert {}, it is not known which of the options applies",
+                self.map(|s| s.as_ref().to_string())
+            ))),
+            EditInstruction::LowercaseAll
+            | EditInstruction::UppercaseAll
+            | EditInstruction::LowercaseSpan(_)
+            | EditInstruction::UppercaseSpan(_) => Err(InvertError(format!(
+                "Unable to invert case transformation {}, the original case is not known",
+                self.map(|s| s.as_ref().to_string())
+            ))),
+        }
+    }
+}
+
+/// Swaps the two units of a transposition. Two grapheme clusters can only result from a
+/// transposition of grapheme clusters, otherwise the units are characters.
+fn swap(s: &str) -> Result<String, InvertError> {
+    let graphemes: Vec<&str> = s.graphemes(true).collect();
+    if let [first, second] = graphemes.as_slice() {
+        return Ok(second.to_string() + first);
+    }
+    let mut chars = s.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(first), Some(second), None) => Ok([second, first].iter().collect()),
+        _ => Err(InvertError(format!(
+            "A transposition takes exactly two units, got '{}'",
+            s
+        ))),
+    }
+}
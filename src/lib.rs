@@ -1,20 +1,182 @@
 extern crate dissimilar;
+extern crate unicode_normalization;
+extern crate unicode_segmentation;
 
-use dissimilar::{diff, Chunk};
-use std::borrow::ToOwned;
+use std::borrow::Cow;
 use std::cmp::PartialEq;
+use std::collections::HashMap;
 use std::fmt;
+use std::ops::Range;
 use std::str::FromStr;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
 
+mod backend;
+mod canonical;
+mod classes;
+mod compose;
+mod costs;
+mod distance;
+mod enumerate;
+mod generalize;
+mod invert;
+mod matching;
+mod myers;
+mod parser;
+mod tiebreaking;
+mod tokens;
+mod trace;
+
+pub use backend::{Algorithm, DiffBackend, DissimilarBackend, LevenshteinBackend, MyersBackend};
+pub use classes::CharClasses;
+pub use costs::CostModel;
+pub use distance::{levenshtein, levenshtein_bounded};
+pub use generalize::{generalize, generalize_with, Generalization};
+pub use myers::DiffOp;
+use parser::{escape, options_payload};
+pub use parser::{Expected, ParseError};
+use tiebreaking::break_ties;
+pub use tiebreaking::TieBreaking;
+pub use tokens::{
+    shortest_edit_script_tokens, shortest_edit_script_tokens_suffix, ApplyTokenEditScript, TokenSeq,
+};
+use trace::Step;
+pub use trace::TraceStep;
+
+/// An error in inverting an edit script, with a message explaining which instruction can not be
+/// inverted
 #[derive(Debug)]
-pub struct ParseError(pub String);
+pub struct InvertError(pub String);
+
+impl fmt::Display for InvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
 
+impl std::error::Error for InvertError {}
+
+/// An error in composing two edit scripts, with a message explaining why they can not be composed
 #[derive(Debug)]
+pub struct ComposeError(pub String);
+
+impl fmt::Display for ComposeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ComposeError {}
+
+/// An error in applying an edit script. Instruction indices start at zero.
+#[derive(Debug, Clone, PartialEq)]
 pub enum ApplyError {
-    NoMatch,
-    WithMessage(String),
+    /// The instruction at `index` does not match the input at `position`, a character offset into
+    /// the (normalized) input, or a token offset for token-level edit scripts. `expected` is what
+    /// the instruction requires there (text, options separated by `|`, a class `{name}` or a
+    /// length `#n`) and `found` is what the input holds instead. The edit script may still apply
+    /// to other input.
+    NoMatch {
+        index: usize,
+        position: usize,
+        expected: String,
+        found: String,
+    },
+    /// The instruction at `index` has multiple insertion options, it is not known which to insert
+    Ambiguous { index: usize },
+    /// The instruction at `index` refers to a character class that is not defined
+    UndefinedClass { index: usize, name: String },
+    /// The instruction at `index` refers to a capture that the edit script does not make
+    UnknownCapture { index: usize, name: String },
+    /// The instruction at `index` can not be applied at all, for the given reason
+    Unsupported { index: usize, reason: String },
+    /// The edit script has no instructions
+    EmptyScript,
+}
+
+impl ApplyError {
+    /// Returns true if the edit script can not apply to any input, rather than just not matching
+    /// this input
+    pub fn is_permanent(&self) -> bool {
+        !matches!(self, ApplyError::NoMatch { .. })
+    }
+
+    /// Adjusts the location for an error that occurred in applying the remainder of an edit
+    /// script (from instruction `index`) to the remainder of the input (from `position`)
+    pub(crate) fn shifted(self, index: usize, position: usize) -> Self {
+        match self {
+            ApplyError::NoMatch {
+                index: i,
+                position: p,
+                expected,
+                found,
+            } => ApplyError::NoMatch {
+                index: index + i,
+                position: position + p,
+                expected,
+                found,
+            },
+            ApplyError::Ambiguous { index: i } => ApplyError::Ambiguous { index: index + i },
+            ApplyError::UndefinedClass { index: i, name } => ApplyError::UndefinedClass {
+                index: index + i,
+                name,
+            },
+            ApplyError::UnknownCapture { index: i, name } => ApplyError::UnknownCapture {
+                index: index + i,
+                name,
+            },
+            ApplyError::Unsupported { index: i, reason } => ApplyError::Unsupported {
+                index: index + i,
+                reason,
+            },
+            ApplyError::EmptyScript => ApplyError::EmptyScript,
+        }
+    }
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApplyError::NoMatch {
+                index,
+                position,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Edit script does not match, instruction {} expects '{}' at position {} but found '{}'",
+                index + 1,
+                expected,
+                position + 1,
+                found
+            ),
+            ApplyError::Ambiguous { index } => write!(
+                f,
+                "Instruction {} has multiple insertion options and is therefore ambiguous, unable to apply",
+                index + 1
+            ),
+            ApplyError::UndefinedClass { index, name } => write!(
+                f,
+                "Instruction {} refers to undefined class {{{}}}",
+                index + 1,
+                name
+            ),
+            ApplyError::UnknownCapture { index, name } => write!(
+                f,
+                "Instruction {} refers to unknown capture {}",
+                index + 1,
+                name
+            ),
+            ApplyError::Unsupported { index, reason } => {
+                write!(f, "Unable to apply instruction {}: {}", index + 1, reason)
+            }
+            ApplyError::EmptyScript => write!(f, "Edit script has no instructions"),
+        }
+    }
 }
 
+impl std::error::Error for ApplyError {}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct EditScript<T> {
     pub mode: Mode,
@@ -36,6 +198,10 @@ pub enum EditInstruction<T> {
     /// An identity check for a specific length (a generic abstraction)
     GenericIdentity(u32),
 
+    /// An identity check for any length, possibly zero. It matches as much as the instructions
+    /// that follow allow.
+    WildcardIdentity,
+
     /// A disjunction over multiple possible insertions
     InsertionOptions(Vec<T>),
 
@@ -44,6 +210,38 @@ pub enum EditInstruction<T> {
     ///
     /// A disjunction over multiple possible identities
     IdentityOptions(Vec<T>),
+
+    /// An identity check for a member of the named character class
+    IdentityClass(T),
+
+    /// A deletion of a member of the named character class
+    DeletionClass(T),
+
+    /// An identity check (any of the identity instructions) that captures the matched material
+    /// under the given name, for back-references
+    Captured(T, Box<EditInstruction<T>>),
+
+    /// An insertion of the material captured under the given name
+    BackReference(T),
+
+    /// A substitution of the first string by the second one, both have the same length
+    Substitution(T, T),
+
+    /// A transposition of two adjacent units, holds them in their original order
+    Transposition(T),
+
+    /// Converts the remainder of the string to lowercase (does not consume anything)
+    LowercaseAll,
+
+    /// Converts the remainder of the string to uppercase (does not consume anything)
+    UppercaseAll,
+
+    /// Converts the next n characters of the remainder to lowercase (does not consume anything)
+    LowercaseSpan(u32),
+
+    /// Converts the next n characters of the remainder to uppercase (does not consume anything), a
+    /// span of one capitalises
+    UppercaseSpan(u32),
 }
 
 impl<T: std::fmt::Display> fmt::Display for EditScript<T> {
@@ -61,139 +259,65 @@ impl<T: std::fmt::Display> fmt::Display for EditInstruction<T> {
             EditInstruction::GenericIdentity(s) => {
                 write!(f, "=[#{}]", s)
             }
+            EditInstruction::WildcardIdentity => {
+                write!(f, "=[*]")
+            }
             EditInstruction::Identity(s) => {
-                write!(f, "=[{}]", s)
+                write!(f, "=[{}]", escape(&s.to_string(), &['#', '*', '{']))
             }
             EditInstruction::Insertion(s) => {
-                write!(f, "+[{}]", s)
+                write!(f, "+[{}]", escape(&s.to_string(), &[]))
             }
             EditInstruction::Deletion(s) => {
-                write!(f, "-[{}]", s)
+                write!(f, "-[{}]", escape(&s.to_string(), &['{']))
             }
             EditInstruction::IdentityOptions(s) => {
-                write!(
-                    f,
-                    "=[{}]",
-                    s.iter()
-                        .map(|x| x.to_string())
-                        .collect::<Vec<String>>()
-                        .join("|")
-                )
+                write!(f, "=[{}]", options_payload(s))
             }
             EditInstruction::InsertionOptions(s) => {
-                write!(
-                    f,
-                    "+[{}]",
-                    s.iter()
-                        .map(|x| x.to_string())
-                        .collect::<Vec<String>>()
-                        .join("|")
-                )
+                write!(f, "+[{}]", options_payload(s))
             }
             EditInstruction::DeletionOptions(s) => {
+                write!(f, "-[{}]", options_payload(s))
+            }
+            EditInstruction::IdentityClass(s) => {
+                write!(f, "=[{{{}}}]", s)
+            }
+            EditInstruction::DeletionClass(s) => {
+                write!(f, "-[{{{}}}]", s)
+            }
+            EditInstruction::Captured(name, instruction) => {
+                //the name goes between the operator and the opening bracket
+                let instruction = instruction.to_string();
+                write!(f, "{}{}{}", &instruction[..1], name, &instruction[1..])
+            }
+            EditInstruction::BackReference(name) => {
+                write!(f, "&[{}]", name)
+            }
+            EditInstruction::Substitution(s, t) => {
                 write!(
                     f,
-                    "-[{}]",
-                    s.iter()
-                        .map(|x| x.to_string())
-                        .collect::<Vec<String>>()
-                        .join("|")
+                    "~[{}>{}]",
+                    escape(&s.to_string(), &[]),
+                    escape(&t.to_string(), &[])
                 )
             }
-        }
-    }
-}
-
-impl FromStr for EditScript<String> {
-    type Err = ParseError;
-
-    fn from_str(editscript: &str) -> Result<Self, Self::Err> {
-        let mut instructions: Vec<EditInstruction<String>> = Vec::new();
-        let mut begin = 0;
-        let mut distance = 0;
-        for (i, c) in editscript.char_indices() {
-            if c == ']' {
-                let instruction = EditInstruction::<String>::from_str(&editscript[begin..i + 1])?;
-                if instruction.is_change() {
-                    distance += 1;
-                }
-                instructions.push(instruction);
-                begin = i + 1;
+            EditInstruction::Transposition(s) => {
+                write!(f, "%[{}]", escape(&s.to_string(), &[]))
             }
-        }
-        if instructions.is_empty() {
-            return Err(ParseError(format!(
-                "Not a valid edit script, no instructions found: {}",
-                editscript
-            )));
-        }
-        Ok(EditScript {
-            distance: distance,
-            instructions: instructions,
-            mode: Mode::Normal,
-        })
-    }
-}
-
-impl FromStr for EditInstruction<String> {
-    type Err = ParseError;
-
-    fn from_str(editinstruction: &str) -> Result<Self, Self::Err> {
-        if editinstruction.len() <= 3 {
-            return Err(ParseError(format!(
-                "String too short to describe a valid edit instruction: {}",
-                editinstruction
-            )));
-        }
-        let mut chariter = editinstruction.chars();
-        let operator = chariter.next(); //first char is operator
-        let startbracket = chariter.next(); //second char is start bracket
-        if startbracket != Some('[') {
-            return Err(ParseError(format!(
-                "Expected start bracket: {}",
-                editinstruction
-            )));
-        }
-        let s = &editinstruction[2..editinstruction.len() - 1];
-        let instruction = match operator {
-            Some('+') => {
-                if s.contains("|") {
-                    EditInstruction::InsertionOptions(s.split("|").collect())
-                } else {
-                    EditInstruction::Insertion(s)
-                }
+            EditInstruction::LowercaseAll => {
+                write!(f, "_[*]")
             }
-            Some('-') => {
-                if s.contains("|") {
-                    EditInstruction::DeletionOptions(s.split("|").collect())
-                } else {
-                    EditInstruction::Deletion(s)
-                }
+            EditInstruction::UppercaseAll => {
+                write!(f, "^[*]")
             }
-            Some('=') => {
-                if s.contains("|") {
-                    if s.chars().nth(0) == Some('#') && s[1..].parse::<u32>().is_ok() {
-                        return Err(ParseError(format!(
-                            "GenericIdentity can not take multiple values"
-                        )));
-                    } else {
-                        EditInstruction::IdentityOptions(s.split("|").collect())
-                    }
-                } else {
-                    if s.chars().nth(0) == Some('#') && s[1..].parse::<u32>().is_ok() {
-                        EditInstruction::GenericIdentity(s[1..].parse::<u32>().unwrap())
-                    } else {
-                        EditInstruction::Identity(s)
-                    }
-                }
+            EditInstruction::LowercaseSpan(n) => {
+                write!(f, "_[#{}]", n)
             }
-            _ => {
-                return Err(ParseError(format!(
-                    "Parsing editscript failed, invalid operator"
-                )))
+            EditInstruction::UppercaseSpan(n) => {
+                write!(f, "^[#{}]", n)
             }
-        };
-        Ok(instruction.to_owned())
+        }
     }
 }
 
@@ -206,6 +330,7 @@ impl EditInstruction<&str> {
             EditInstruction::Deletion(s) => EditInstruction::Deletion(s.to_string()),
             EditInstruction::Identity(s) => EditInstruction::Identity(s.to_string()),
             EditInstruction::GenericIdentity(n) => EditInstruction::GenericIdentity(*n),
+            EditInstruction::WildcardIdentity => EditInstruction::WildcardIdentity,
             EditInstruction::InsertionOptions(v) => {
                 EditInstruction::InsertionOptions(v.iter().map(|s| s.to_string()).collect())
             }
@@ -215,6 +340,20 @@ impl EditInstruction<&str> {
             EditInstruction::IdentityOptions(v) => {
                 EditInstruction::IdentityOptions(v.iter().map(|s| s.to_string()).collect())
             }
+            EditInstruction::Substitution(s, t) => {
+                EditInstruction::Substitution(s.to_string(), t.to_string())
+            }
+            EditInstruction::IdentityClass(s) => EditInstruction::IdentityClass(s.to_string()),
+            EditInstruction::DeletionClass(s) => EditInstruction::DeletionClass(s.to_string()),
+            EditInstruction::Captured(name, instruction) => {
+                EditInstruction::Captured(name.to_string(), Box::new((**instruction).to_owned()))
+            }
+            EditInstruction::BackReference(s) => EditInstruction::BackReference(s.to_string()),
+            EditInstruction::Transposition(s) => EditInstruction::Transposition(s.to_string()),
+            EditInstruction::LowercaseAll => EditInstruction::LowercaseAll,
+            EditInstruction::UppercaseAll => EditInstruction::UppercaseAll,
+            EditInstruction::LowercaseSpan(n) => EditInstruction::LowercaseSpan(*n),
+            EditInstruction::UppercaseSpan(n) => EditInstruction::UppercaseSpan(*n),
         }
     }
 }
@@ -236,6 +375,7 @@ impl EditInstruction<String> {
             EditInstruction::Deletion(s) => EditInstruction::Deletion(s.as_str()),
             EditInstruction::Identity(s) => EditInstruction::Identity(s.as_str()),
             EditInstruction::GenericIdentity(n) => EditInstruction::GenericIdentity(*n),
+            EditInstruction::WildcardIdentity => EditInstruction::WildcardIdentity,
             EditInstruction::InsertionOptions(v) => {
                 EditInstruction::InsertionOptions(v.iter().map(|s| s.as_str()).collect())
             }
@@ -245,6 +385,20 @@ impl EditInstruction<String> {
             EditInstruction::IdentityOptions(v) => {
                 EditInstruction::IdentityOptions(v.iter().map(|s| s.as_str()).collect())
             }
+            EditInstruction::Substitution(s, t) => {
+                EditInstruction::Substitution(s.as_str(), t.as_str())
+            }
+            EditInstruction::IdentityClass(s) => EditInstruction::IdentityClass(s.as_str()),
+            EditInstruction::DeletionClass(s) => EditInstruction::DeletionClass(s.as_str()),
+            EditInstruction::Captured(name, instruction) => {
+                EditInstruction::Captured(name.as_str(), Box::new((**instruction).as_ref()))
+            }
+            EditInstruction::BackReference(s) => EditInstruction::BackReference(s.as_str()),
+            EditInstruction::Transposition(s) => EditInstruction::Transposition(s.as_str()),
+            EditInstruction::LowercaseAll => EditInstruction::LowercaseAll,
+            EditInstruction::UppercaseAll => EditInstruction::UppercaseAll,
+            EditInstruction::LowercaseSpan(n) => EditInstruction::LowercaseSpan(*n),
+            EditInstruction::UppercaseSpan(n) => EditInstruction::UppercaseSpan(*n),
         }
     }
 }
@@ -263,6 +417,10 @@ impl<T> EditScript<T> {
     pub fn len(&self) -> usize {
         self.instructions.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
 }
 
 impl<T> EditInstruction<T> {
@@ -271,14 +429,56 @@ impl<T> EditInstruction<T> {
             EditInstruction::Insertion(_) | EditInstruction::Deletion(_) => true,
             EditInstruction::Identity(_) => false,
             EditInstruction::GenericIdentity(_) => false,
+            EditInstruction::WildcardIdentity => false,
             EditInstruction::InsertionOptions(_) | EditInstruction::DeletionOptions(_) => true,
             EditInstruction::IdentityOptions(_) => false,
+            EditInstruction::IdentityClass(_) => false,
+            EditInstruction::DeletionClass(_) => true,
+            EditInstruction::Captured(_, instruction) => instruction.is_change(),
+            EditInstruction::BackReference(_) => true,
+            EditInstruction::Substitution(_, _) => true,
+            EditInstruction::Transposition(_) => true,
+            EditInstruction::LowercaseAll | EditInstruction::UppercaseAll => true,
+            EditInstruction::LowercaseSpan(_) | EditInstruction::UppercaseSpan(_) => true,
+        }
+    }
+
+    /// Returns a copy of the instruction where all string contents are mapped using the provided function
+    pub(crate) fn map<'a, U>(&'a self, f: impl Fn(&'a T) -> U) -> EditInstruction<U> {
+        match self {
+            EditInstruction::Insertion(s) => EditInstruction::Insertion(f(s)),
+            EditInstruction::Deletion(s) => EditInstruction::Deletion(f(s)),
+            EditInstruction::Identity(s) => EditInstruction::Identity(f(s)),
+            EditInstruction::GenericIdentity(n) => EditInstruction::GenericIdentity(*n),
+            EditInstruction::WildcardIdentity => EditInstruction::WildcardIdentity,
+            EditInstruction::InsertionOptions(v) => {
+                EditInstruction::InsertionOptions(v.iter().map(&f).collect())
+            }
+            EditInstruction::DeletionOptions(v) => {
+                EditInstruction::DeletionOptions(v.iter().map(&f).collect())
+            }
+            EditInstruction::IdentityOptions(v) => {
+                EditInstruction::IdentityOptions(v.iter().map(&f).collect())
+            }
+            EditInstruction::Substitution(s, t) => EditInstruction::Substitution(f(s), f(t)),
+            EditInstruction::IdentityClass(s) => EditInstruction::IdentityClass(f(s)),
+            EditInstruction::DeletionClass(s) => EditInstruction::DeletionClass(f(s)),
+            EditInstruction::Captured(name, instruction) => {
+                EditInstruction::Captured(f(name), Box::new(instruction.map(f)))
+            }
+            EditInstruction::BackReference(s) => EditInstruction::BackReference(f(s)),
+            EditInstruction::Transposition(s) => EditInstruction::Transposition(f(s)),
+            EditInstruction::LowercaseAll => EditInstruction::LowercaseAll,
+            EditInstruction::UppercaseAll => EditInstruction::UppercaseAll,
+            EditInstruction::LowercaseSpan(n) => EditInstruction::LowercaseSpan(*n),
+            EditInstruction::UppercaseSpan(n) => EditInstruction::UppercaseSpan(*n),
         }
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
 pub enum Mode {
+    #[default]
     Normal,
     Suffix,
     Prefix,
@@ -288,9 +488,261 @@ pub enum Mode {
     Infix,
 }
 
-impl Default for Mode {
+/// The unit of text that edit scripts are computed on and applied to. This determines what
+/// the lengths in the script (such as `=[#n]`) count.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum Unit {
+    /// Unicode scalar values (chars)
+    #[default]
+    Char,
+
+    /// Extended grapheme clusters (user-perceived characters); a cluster is never split between
+    /// instructions
+    Grapheme,
+}
+
+impl Unit {
+    /// Returns the length of the string expressed in this unit
+    pub fn count(&self, s: &str) -> usize {
+        match self {
+            Unit::Char => s.chars().count(),
+            Unit::Grapheme => s.graphemes(true).count(),
+        }
+    }
+
+    /// Splits the string into its units
+    pub(crate) fn split<'a>(&self, s: &'a str) -> Vec<&'a str> {
+        match self {
+            Unit::Char => s
+                .char_indices()
+                .map(|(i, c)| &s[i..i + c.len_utf8()])
+                .collect(),
+            Unit::Grapheme => s.graphemes(true).collect(),
+        }
+    }
+
+    /// Is the byte offset a boundary between two units in the string?
+    fn is_boundary(&self, s: &str, offset: usize) -> bool {
+        match self {
+            Unit::Char => s.is_char_boundary(offset),
+            Unit::Grapheme => {
+                s.is_char_boundary(offset)
+                    && GraphemeCursor::new(offset, s.len(), true)
+                        .is_boundary(s, 0)
+                        .unwrap_or(false)
+            }
+        }
+    }
+
+    /// Returns the byte offset that lies `n` units after the given offset, if any
+    fn forward(&self, s: &str, offset: usize, n: usize) -> Option<usize> {
+        if n == 0 {
+            return Some(offset);
+        }
+        match self {
+            Unit::Char => s[offset..]
+                .char_indices()
+                .map(|(i, c)| offset + i + c.len_utf8())
+                .nth(n - 1),
+            Unit::Grapheme => s[offset..]
+                .grapheme_indices(true)
+                .map(|(i, g)| offset + i + g.len())
+                .nth(n - 1),
+        }
+    }
+
+    /// Returns the byte offset that lies `n` units before the given offset, if any
+    fn backward(&self, s: &str, offset: usize, n: usize) -> Option<usize> {
+        if n == 0 {
+            return Some(offset);
+        }
+        match self {
+            Unit::Char => s[..offset].char_indices().rev().map(|(i, _)| i).nth(n - 1),
+            Unit::Grapheme => s[..offset]
+                .grapheme_indices(true)
+                .rev()
+                .map(|(i, _)| i)
+                .nth(n - 1),
+        }
+    }
+}
+
+/// Unicode normalization forms, strings can be normalized prior to computing or applying edit
+/// scripts so that the same text in different forms yields the same results
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Normalization {
+    /// Canonical decomposition followed by canonical composition
+    NFC,
+    /// Canonical decomposition
+    NFD,
+    /// Compatibility decomposition followed by canonical composition
+    NFKC,
+    /// Compatibility decomposition
+    NFKD,
+}
+
+impl Normalization {
+    /// Returns the normalized form of the string
+    pub fn normalize(&self, s: &str) -> String {
+        match self {
+            Normalization::NFC => s.nfc().collect(),
+            Normalization::NFD => s.nfd().collect(),
+            Normalization::NFKC => s.nfkc().collect(),
+            Normalization::NFKD => s.nfkd().collect(),
+        }
+    }
+}
+
+impl FromStr for Normalization {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "nfc" => Ok(Normalization::NFC),
+            "nfd" => Ok(Normalization::NFD),
+            "nfkc" => Ok(Normalization::NFKC),
+            "nfkd" => Ok(Normalization::NFKD),
+            _ => Err(ParseError::WithMessage(format!(
+                "Unknown normalization form: {} (expected NFC, NFD, NFKC or NFKD)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Policy for choosing between the alternatives of options (e.g. `-[n|en]`) when more than one
+/// matches. If the preferred alternative makes later instructions fail, the next one is tried.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum OptionMatching {
+    /// Prefer the alternatives in the order in which they are listed
+    #[default]
+    First,
+
+    /// Prefer the alternative that matches the most input
+    Longest,
+
+    /// Prefer the alternative that matches the least input
+    Shortest,
+}
+
+impl OptionMatching {
+    /// Returns the sort key for an alternative that matches `length` bytes, lower is preferred
+    fn rank(&self, length: usize) -> isize {
+        match self {
+            OptionMatching::First => 0,
+            OptionMatching::Longest => -(length as isize),
+            OptionMatching::Shortest => length as isize,
+        }
+    }
+
+    /// Chooses between the alternatives that match, given by the lengths of their matches in list
+    /// order: the preferred one after which the rest of the edit script applies as well, or else
+    /// the preferred one
+    fn choose(
+        &self,
+        lengths: impl IntoIterator<Item = usize>,
+        rest_applies: impl Fn(usize) -> bool,
+    ) -> Option<usize> {
+        let mut candidates: Vec<usize> = Vec::new();
+        for length in lengths {
+            if !candidates.contains(&length) {
+                candidates.push(length);
+            }
+        }
+        candidates.sort_by_key(|length| self.rank(*length));
+        if candidates.len() < 2 {
+            return candidates.first().copied();
+        }
+        candidates
+            .iter()
+            .copied()
+            .find(|length| rest_applies(*length))
+            .or(candidates.first().copied())
+    }
+}
+
+impl FromStr for OptionMatching {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "first" => Ok(OptionMatching::First),
+            "longest" => Ok(OptionMatching::Longest),
+            "shortest" => Ok(OptionMatching::Shortest),
+            _ => Err(ParseError::WithMessage(format!(
+                "Unknown option matching policy: {} (expected first, longest or shortest)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Options for computing edit scripts with [`shortest_edit_script_with()`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffOptions {
+    /// Normal, Prefix (ignores common suffixes) or Suffix mode (operates from right to left and ignores common prefixes)
+    pub mode: Mode,
+
+    /// Refer to unchanged parts by their length only (`=[#n]`)
+    pub generic: bool,
+
+    /// Count substitutions as a single edit in the distance
+    pub allow_substitutions: bool,
+
+    /// The unit the edit script is computed on
+    pub unit: Unit,
+
+    /// Normalize source and target to this unicode normalization form prior to computing the edit script
+    pub normalization: Option<Normalization>,
+
+    /// Express changes in letter case with case instructions rather than with deletions and insertions
+    pub factor_case: bool,
+
+    /// Express two swapped adjacent units as a single transposition (counting as one edit) rather
+    /// than as a deletion and an insertion
+    pub allow_transpositions: bool,
+
+    /// The costs of the edit operations, these determine the distance of the edit script
+    pub costs: CostModel,
+
+    /// The diff algorithm that computes the alignment between source and target
+    pub algorithm: Algorithm,
+
+    /// The policy for choosing between alignments of equal cost, if not set this is up to the diff
+    /// algorithm
+    pub tie_breaking: Option<TieBreaking>,
+
+    /// Keep at most this many units of each unchanged part literally, adjacent to the changes,
+    /// and refer to the rest by its length only (`=[#n]`). This takes precedence over `generic`.
+    pub context: Option<usize>,
+
+    /// Express unchanged parts at the start and end that are referred to by their length only
+    /// (see `generic` and `context`) as a wildcard (`=[*]`) that matches any length instead, as far
+    /// as the edit script still maps the source onto the target
+    pub wildcards: bool,
+
+    /// Express insertions that copy unchanged material, such as reduplications, as back-references
+    /// (`&[1]`) to a captured identity (`=1[...]`)
+    pub captures: bool,
+}
+
+impl Default for DiffOptions {
     fn default() -> Self {
-        Self::Normal
+        Self {
+            mode: Mode::Normal,
+            generic: false,
+            allow_substitutions: true,
+            unit: Unit::Char,
+            normalization: None,
+            factor_case: false,
+            allow_transpositions: false,
+            costs: CostModel::default(),
+            algorithm: Algorithm::Dissimilar,
+            tie_breaking: None,
+            context: None,
+            wildcards: false,
+            captures: false,
+        }
     }
 }
 
@@ -303,64 +755,16 @@ pub fn shortest_edit_script<'a>(
     generic: bool,
     allow_substitutions: bool,
 ) -> EditScript<&'a str> {
-    let diffchunks: Vec<Chunk> = diff(&source, &target);
-    let mut prev: isize = 0;
-    let mut distance = 0;
-    let mut abort_at = None;
-    if prefix {
-        let mut tail = 0;
-        for chunk in diffchunks.iter() {
-            if let Chunk::Equal(_) = chunk {
-                tail += 1;
-            } else {
-                tail = 0;
-            }
-        }
-        abort_at = Some(diffchunks.len() - tail);
-    }
-    let mut instructions: Vec<EditInstruction<&'a str>> =
-        Vec::with_capacity(abort_at.unwrap_or(diffchunks.len()));
-    for (i, chunk) in diffchunks.iter().enumerate() {
-        if abort_at.is_some() && i == abort_at.unwrap() {
-            break;
-        }
-        match chunk {
-            Chunk::Equal(s) => {
-                if generic {
-                    instructions.push(EditInstruction::GenericIdentity(s.chars().count() as u32));
-                } else {
-                    instructions.push(EditInstruction::Identity(s));
-                }
-                prev = 0;
-            }
-            Chunk::Delete(s) => {
-                let length: isize = s.chars().count() as isize;
-                let is_substitution = prev > 0 && length == prev;
-                if !is_substitution || !allow_substitutions {
-                    distance += length;
-                }
-                instructions.push(EditInstruction::Deletion(s));
-                prev = length * -1;
-            }
-            Chunk::Insert(s) => {
-                let length: isize = s.chars().count() as isize;
-                let is_substitution = prev < 0 && s.len() as isize * -1 == prev;
-                if !is_substitution || !allow_substitutions {
-                    distance += length;
-                }
-                instructions.push(EditInstruction::Insertion(s));
-                prev = length;
-            }
-        }
-    }
-    EditScript {
-        instructions: instructions,
+    let options = DiffOptions {
         mode: match prefix {
             true => Mode::Prefix,
             false => Mode::Normal,
         },
-        distance: distance as u32,
-    }
+        generic,
+        allow_substitutions,
+        ..Default::default()
+    };
+    compute_edit_script(source, target, &options, options.algorithm.backend())
 }
 
 /// Compute the shortest edit script (Myers' diff) between source and target where we look at
@@ -372,392 +776,1545 @@ pub fn shortest_edit_script_suffix(
     generic: bool,
     allow_substitutions: bool,
 ) -> EditScript<String> {
-    let source = source.to_owned().chars().rev().collect::<String>();
-    let target = target.to_owned().chars().rev().collect::<String>();
-    let diffchunks: Vec<Chunk> = diff(source.as_str(), target.as_str());
-    let mut prev: isize = 0;
-    let mut distance = 0;
-    let abort_at = {
-        let mut tail = 0;
-        for chunk in diffchunks.iter() {
-            if let Chunk::Equal(_) = chunk {
-                tail += 1;
-            } else {
-                tail = 0;
-            }
-        }
-        Some(diffchunks.len() - tail)
-    };
-    let mut instructions: Vec<EditInstruction<String>> =
-        Vec::with_capacity(abort_at.unwrap_or(diffchunks.len()));
-    for (i, chunk) in diffchunks.iter().enumerate() {
-        if i == abort_at.unwrap() {
-            break;
-        }
-        match chunk {
-            Chunk::Equal(s) => {
-                if generic {
-                    instructions.push(EditInstruction::GenericIdentity(s.chars().count() as u32));
-                } else {
-                    instructions.push(EditInstruction::Identity(
-                        s.to_owned().chars().rev().collect::<String>(),
-                    ));
-                }
-                prev = 0;
-            }
-            Chunk::Delete(s) => {
-                let length: isize = s.chars().count() as isize;
-                let is_substitution = prev > 0 && length == prev;
-                if !is_substitution || !allow_substitutions {
-                    distance += length;
-                }
-                instructions.push(EditInstruction::Deletion(
-                    s.to_owned().chars().rev().collect::<String>(),
-                ));
-                prev = length * -1;
-            }
-            Chunk::Insert(s) => {
-                let length: isize = s.chars().count() as isize;
-                let is_substitution = prev < 0 && s.len() as isize * -1 == prev;
-                if !is_substitution || !allow_substitutions {
-                    distance += length;
-                }
-                instructions.push(EditInstruction::Insertion(
-                    s.to_owned().chars().rev().collect::<String>(),
-                ));
-                prev = length;
-            }
-        }
-    }
-    EditScript {
-        instructions: instructions,
+    let options = DiffOptions {
         mode: Mode::Suffix,
-        distance: distance as u32,
-    }
+        generic,
+        allow_substitutions,
+        ..Default::default()
+    };
+    compute_edit_script(source, target, &options, options.algorithm.backend()).to_owned()
 }
 
-pub trait ApplyEditScript {
-    fn apply_to(&self, input: &str, mode: Option<Mode>) -> Result<String, ApplyError>;
+/// Compute the shortest edit script between source and target according to the specified
+/// options. This covers the normal, prefix and suffix modes.
+/// Returns an edit script with owned strings
+pub fn shortest_edit_script_with(
+    source: &str,
+    target: &str,
+    options: &DiffOptions,
+) -> EditScript<String> {
+    shortest_edit_script_with_backend(source, target, options, options.algorithm.backend())
 }
 
-impl ApplyEditScript for EditScript<String> {
-    fn apply_to(&self, input: &str, mode: Option<Mode>) -> Result<String, ApplyError> {
-        self.as_ref().apply_to(input, mode)
+/// Compute the shortest edit script between source and target according to the specified
+/// options, using a custom diff backend rather than the algorithm set in the options.
+/// Returns an edit script with owned strings
+pub fn shortest_edit_script_with_backend(
+    source: &str,
+    target: &str,
+    options: &DiffOptions,
+    backend: &dyn DiffBackend,
+) -> EditScript<String> {
+    let mut source = Cow::Borrowed(source);
+    let mut target = Cow::Borrowed(target);
+    if let Some(normalization) = options.normalization {
+        source = Cow::Owned(normalization.normalize(&source));
+        target = Cow::Owned(normalization.normalize(&target));
+    }
+    if options.factor_case {
+        case_factored_edit_script(&source, &target, options, backend)
+    } else {
+        compute_edit_script(&source, &target, options, backend).to_owned()
     }
 }
 
-///auxiliary internal function for apply_to() in normal/prefix mode
-fn instruction_applies(
-    instructioncontent: &str,
-    input: &str,
-    tail: &Option<String>,
-    tailchars: usize,
-) -> Result<usize, ApplyError> {
-    let instructionlength = instructioncontent.chars().count();
-    if instructionlength > tailchars {
-        return Err(ApplyError::NoMatch);
-        //return Err(ApplyError(format!("Edit script does not match current word, prefix is longer than head (unable to remove prefix {})", prefix)));
-    }
-    let refcontent = if let Some(tail) = tail {
-        &tail[..instructionlength]
+fn compute_edit_script<'a>(
+    source: &'a str,
+    target: &'a str,
+    options: &DiffOptions,
+    backend: &dyn DiffBackend,
+) -> EditScript<&'a str> {
+    let ops = align(source, target, options, backend);
+    build_edit_script(source, target, ops, options)
+}
+
+/// Computes an edit script where changes in letter case are expressed by case instructions
+fn case_factored_edit_script(
+    source: &str,
+    target: &str,
+    options: &DiffOptions,
+    backend: &dyn DiffBackend,
+) -> EditScript<String> {
+    //a change of case that affects the entire string is expressed in a single instruction
+    let (global, source) = if target.to_lowercase() == target
+        && target.to_uppercase() != target
+        && source.to_lowercase() != source
+    {
+        (
+            Some(EditInstruction::LowercaseAll),
+            Cow::Owned(source.to_lowercase()),
+        )
+    } else if target.to_uppercase() == target
+        && target.to_lowercase() != target
+        && source.to_uppercase() != source
+    {
+        (
+            Some(EditInstruction::UppercaseAll),
+            Cow::Owned(source.to_uppercase()),
+        )
     } else {
-        &input[..instructionlength]
+        (None, Cow::Borrowed(source))
     };
-    if refcontent != instructioncontent {
-        return Err(ApplyError::NoMatch);
-    } else {
-        Ok(instructionlength)
+    //the alignment is computed on case-folded strings (with identical byte offsets), remaining
+    //differences in case within equal stretches are resolved when building the edit script
+    let ops = align(&fold_case(&source), &fold_case(target), options, backend);
+    let mut editscript = build_edit_script(&source, target, ops, options);
+    if let Some(instruction) = global {
+        editscript.instructions.insert(0, instruction);
+        editscript.distance += options.costs.case;
     }
+    editscript.to_owned()
 }
 
-impl ApplyEditScript for EditScript<&str> {
-    fn apply_to(&self, input: &str, mode: Option<Mode>) -> Result<String, ApplyError> {
-        let mode = if let Some(mode) = mode {
-            mode
-        } else {
-            self.mode
-        };
-
-        if mode == Mode::Infix {
-            /////////////////////////////////// INFIX MODE
-
-            //iterate over the input attempting to match at each stage
-
-            let mut head: Option<String> = None;
-
-            let mut begin = 0;
-            let mut skip = 0;
-
-            for (i, _) in input.char_indices() {
-                if skip > 0 {
-                    skip -= 1;
-                    continue;
-                }
-                if let Ok(result) = self.apply_to(&input[i..], Some(Mode::Normal)) {
-                    //we override the mode and recurse for each position
-                    if head.is_none() {
-                        head = Some(String::new())
-                    }; //init
-                    skip = result.chars().count();
-                    head.as_mut().map(|h| {
-                        if i > 0 && i > begin {
-                            *h += &input[begin..i];
-                        }
-                        *h += result.as_str()
-                    });
-                    begin = i + skip;
-                }
+/// Converts all characters to lowercase, except those whose lowercase form has a different
+/// length (so byte offsets in the result correspond to those in the original)
+fn fold_case(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            let mut lowercase = c.to_lowercase();
+            match (lowercase.next(), lowercase.next()) {
+                (Some(l), None) if l.len_utf8() == c.len_utf8() => l,
+                _ => c,
             }
+        })
+        .collect()
+}
 
-            if let Some(mut head) = head.take() {
-                if begin < input.chars().count() {
-                    head += &input[begin..];
-                }
-                Ok(head)
-            } else {
-                Err(ApplyError::NoMatch)
-            }
-        } else if mode == Mode::Suffix {
-            /////////////////////////////////// SUFFIX MODE
-            let mut head: String = input.to_string();
-            let mut tail = String::new();
-            for instruction in self.instructions.iter() {
-                let headchars = head.chars().count();
-                /*
-                eprintln!("DEBUG: Instruction: {}", instruction);
-                eprintln!("              Head: {}", head);
-                eprintln!("              Tail: {}", tail);*/
-                match instruction {
-                    EditInstruction::Deletion(suffix) => {
-                        let suffixchars = suffix.chars().count();
-                        if suffixchars > headchars {
-                            return Err(ApplyError::WithMessage(format!("Edit script does not match current word, suffix is longer than head (unable to remove suffix {})", suffix)));
-                        }
-                        let foundsuffix: String = head
-                            .chars()
-                            .skip(headchars - suffixchars)
-                            .take(suffixchars)
-                            .collect();
-                        if foundsuffix.as_str() != *suffix {
-                            return Err(ApplyError::WithMessage(format!("Edit script does not match current word (unable to find and remove suffix '{}', found '{}' instead)", suffix, foundsuffix)));
-                        }
-                        head = head.chars().take(headchars - suffixchars).collect();
-                    }
-                    EditInstruction::Insertion(s) => {
-                        tail.insert_str(0, s);
-                    }
-                    EditInstruction::GenericIdentity(keeplength) => {
-                        let keeplength = *keeplength as usize;
-                        if keeplength > headchars {
-                            return Err(ApplyError::WithMessage(format!("Edit script does not match current word, length to keep is longer than head")));
-                        }
-                        tail = head
-                            .chars()
-                            .skip(headchars - keeplength)
-                            .take(keeplength)
-                            .collect::<String>()
-                            + tail.as_str();
-                        head = head.chars().take(headchars - keeplength).collect();
-                    }
-                    EditInstruction::Identity(suffix) => {
-                        let suffixchars = suffix.chars().count();
-                        if suffixchars > headchars {
-                            return Err(ApplyError::WithMessage(format!("Edit script does not match current word, suffix is longer than head (unable to keep suffix {})", suffix)));
-                        }
-                        let foundsuffix: String = head
-                            .chars()
-                            .skip(headchars - suffixchars)
-                            .take(suffixchars)
-                            .collect();
-                        if foundsuffix.as_str() != *suffix {
-                            return Err(ApplyError::WithMessage(format!("Edit script does not match current word (unable to find and keep suffix {})", suffix)));
-                        }
-                        tail = head
-                            .chars()
-                            .skip(headchars - suffixchars)
-                            .take(suffixchars)
-                            .collect::<String>()
-                            + tail.as_str();
-                        head = head.chars().take(headchars - suffixchars).collect();
-                    }
-                    EditInstruction::IdentityOptions(suffixes) => {
-                        for suffix in suffixes {
-                            let suffixchars = suffix.chars().count();
-                            if suffixchars > headchars {
-                                continue; //no match
-                            }
-                            let foundsuffix: String = head
-                                .chars()
-                                .skip(headchars - suffixchars)
-                                .take(suffixchars)
-                                .collect();
-                            if foundsuffix.as_str() == *suffix {
-                                //match, apply
-                                tail = head
-                                    .chars()
-                                    .skip(headchars - suffixchars)
-                                    .take(suffixchars)
-                                    .collect::<String>()
-                                    + tail.as_str();
-                                head = head.chars().take(headchars - suffixchars).collect();
-                                break;
-                            }
-                        }
-                    }
-                    EditInstruction::DeletionOptions(suffixes) => {
-                        for suffix in suffixes {
-                            let suffixchars = suffix.chars().count();
-                            if suffixchars > headchars {
-                                continue; //no match
-                            }
-                            let foundsuffix: String = head
-                                .chars()
-                                .skip(headchars - suffixchars)
-                                .take(suffixchars)
-                                .collect();
-                            if foundsuffix.as_str() == *suffix {
-                                //match, apply
-                                head = head.chars().take(headchars - suffixchars).collect();
-                                break;
-                            }
-                        }
+/// Aligns source and target, returns the operations in the order in which they will appear in
+/// the edit script
+fn align(
+    source: &str,
+    target: &str,
+    options: &DiffOptions,
+    backend: &dyn DiffBackend,
+) -> Vec<DiffOp> {
+    let suffix = options.mode == Mode::Suffix;
+    let mut ops = if suffix {
+        //operate on reversed strings and map the result back onto the original strings
+        let reversed_source = source.chars().rev().collect::<String>();
+        let reversed_target = target.chars().rev().collect::<String>();
+        let mut ops = backend.diff(&reversed_source, &reversed_target);
+        if let Some(policy) = options.tie_breaking {
+            ops = break_ties(&reversed_source, &reversed_target, ops, policy, true);
+        }
+        for op in ops.iter_mut() {
+            *op = match op {
+                DiffOp::Equal(s, t) => DiffOp::Equal(
+                    source.len() - s.end..source.len() - s.start,
+                    target.len() - t.end..target.len() - t.start,
+                ),
+                DiffOp::Delete(s) => DiffOp::Delete(source.len() - s.end..source.len() - s.start),
+                DiffOp::Insert(t) => DiffOp::Insert(target.len() - t.end..target.len() - t.start),
+            };
+        }
+        ops
+    } else {
+        let ops = backend.diff(source, target);
+        match options.tie_breaking {
+            Some(policy) => break_ties(source, target, ops, policy, false),
+            None => ops,
+        }
+    };
+    if options.unit == Unit::Grapheme {
+        if suffix {
+            ops.reverse();
+        }
+        ops = snap_to_boundaries(source, target, ops, options.unit);
+        if suffix {
+            ops.reverse();
+        }
+        ops = merge_changes(ops);
+    }
+    ops
+}
+
+/// Builds the edit script from the aligned operations
+fn build_edit_script<'a>(
+    source: &'a str,
+    target: &'a str,
+    ops: Vec<DiffOp>,
+    options: &DiffOptions,
+) -> EditScript<&'a str> {
+    let suffix = options.mode == Mode::Suffix;
+    let mut prev: isize = 0;
+    let mut instructions: Vec<EditInstruction<&'a str>> = Vec::with_capacity(ops.len());
+    let mut ops = ops.into_iter();
+    while let Some(op) = ops.next() {
+        if options.allow_transpositions {
+            let next: Vec<DiffOp> = ops.clone().take(2).collect();
+            if let Some((s, skip)) = transposition(source, target, &op, &next, options.unit) {
+                instructions.push(EditInstruction::Transposition(&source[s]));
+                prev = 0;
+                for _ in 0..skip {
+                    ops.next();
+                }
+                continue;
+            }
+        }
+        match op {
+            DiffOp::Equal(s, t) if source[s.clone()] != target[t.clone()] => {
+                //equal except for letter case (only when factoring out case)
+                push_case_changes(
+                    &mut instructions,
+                    &source[s],
+                    &target[t],
+                    options,
+                    &mut prev,
+                );
+            }
+            DiffOp::Equal(s, _) => {
+                instructions.push(EditInstruction::Identity(&source[s]));
+                prev = 0;
+            }
+            DiffOp::Delete(s) => {
+                let s = &source[s];
+                let length: isize = options.unit.count(s) as isize;
+                let is_substitution = prev > 0 && length == prev;
+                if is_substitution && options.allow_substitutions {
+                    if let Some(EditInstruction::Insertion(t)) = instructions.pop() {
+                        instructions.push(EditInstruction::Substitution(s, t));
                     }
-                    EditInstruction::InsertionOptions(_) => {
-                        return Err(ApplyError::WithMessage(format!("Edit script has multiple insertion options and is therefor ambiguous, unable to apply")));
+                    prev = 0;
+                } else {
+                    instructions.push(EditInstruction::Deletion(s));
+                    prev = -length;
+                }
+            }
+            DiffOp::Insert(t) => {
+                let t = &target[t];
+                let length: isize = options.unit.count(t) as isize;
+                let is_substitution = prev < 0 && -length == prev;
+                if is_substitution && options.allow_substitutions {
+                    if let Some(EditInstruction::Deletion(s)) = instructions.pop() {
+                        instructions.push(EditInstruction::Substitution(s, t));
                     }
+                    prev = 0;
+                } else {
+                    instructions.push(EditInstruction::Insertion(t));
+                    prev = length;
+                }
+            }
+        }
+    }
+    if options.captures {
+        instructions = detect_captures(instructions, options.unit, suffix);
+    }
+    if options.mode == Mode::Prefix || suffix {
+        //common suffixes (prefix mode) or common prefixes (suffix mode) are not part of the script
+        while let Some(EditInstruction::Identity(_)) | Some(EditInstruction::GenericIdentity(_)) =
+            instructions.last()
+        {
+            instructions.pop();
+        }
+    }
+    if let Some(context) = options.context {
+        instructions = restrict_context(instructions, context, options.unit, suffix);
+    } else if options.generic {
+        for instruction in instructions.iter_mut() {
+            abstract_identity(instruction, options.unit);
+        }
+    }
+    if options.wildcards && !instructions.is_empty() {
+        instructions = apply_wildcards(instructions, source, target, options);
+    }
+    EditScript {
+        mode: match options.mode {
+            Mode::Prefix => Mode::Prefix,
+            Mode::Suffix => Mode::Suffix,
+            _ => Mode::Normal,
+        },
+        distance: options.costs.distance(&instructions, options.unit),
+        instructions,
+    }
+}
+
+/// Refers to a (possibly captured) identity by its length only
+fn abstract_identity(instruction: &mut EditInstruction<&str>, unit: Unit) {
+    match instruction {
+        EditInstruction::Identity(s) => {
+            *instruction = EditInstruction::GenericIdentity(unit.count(s) as u32)
+        }
+        EditInstruction::Captured(_, instruction) => abstract_identity(instruction, unit),
+        _ => {}
+    }
+}
+
+/// Turns a (possibly captured) identity that is referred to by its length into a wildcard
+fn make_wildcard(instruction: &mut EditInstruction<&str>) {
+    match instruction {
+        EditInstruction::GenericIdentity(_) => *instruction = EditInstruction::WildcardIdentity,
+        EditInstruction::Captured(_, instruction) => make_wildcard(instruction),
+        _ => {}
+    }
+}
+
+/// Expresses the abstracted unchanged parts at either end as wildcards, as far as the edit script
+/// still maps the source onto the target. A wildcard matches greedily, so where it would move the
+/// other instructions, the unchanged part keeps its length instead.
+fn apply_wildcards<'a>(
+    instructions: Vec<EditInstruction<&'a str>>,
+    source: &str,
+    target: &str,
+    options: &DiffOptions,
+) -> Vec<EditInstruction<&'a str>> {
+    let mode = match options.mode {
+        Mode::Infix => Mode::Normal,
+        mode => mode,
+    };
+    let applyoptions = ApplyOptions {
+        unit: options.unit,
+        ..Default::default()
+    };
+    let end = instructions.len() - 1;
+    //wildcards at both ends, at the start only, at the end only
+    for (first, last) in [(true, true), (true, false), (false, true)] {
+        let mut candidate = instructions.clone();
+        if first {
+            make_wildcard(&mut candidate[0]);
+        }
+        //in prefix and suffix mode, the last instruction borders on the part that is left out, so
+        //captures there must retain their length
+        if last
+            && (mode == Mode::Normal || !matches!(candidate[end], EditInstruction::Captured(..)))
+        {
+            make_wildcard(&mut candidate[end]);
+        }
+        if candidate == instructions {
+            continue;
+        }
+        let editscript = EditScript {
+            mode,
+            distance: 0,
+            instructions: candidate,
+        };
+        if apply_script(&editscript, source, &applyoptions, None)
+            .is_ok_and(|output| output == target)
+        {
+            return editscript.instructions;
+        }
+    }
+    instructions
+}
+
+/// Names of the captures in generated edit scripts
+const CAPTURE_NAMES: [&str; 9] = ["1", "2", "3", "4", "5", "6", "7", "8", "9"];
+
+/// Expresses insertions that copy unchanged material as back-references to captured identities.
+/// An insertion may contain an entire identity of at least two units (reduplication), or repeat
+/// the end of the identity before it or the start of the one after it (gemination).
+fn detect_captures<'a>(
+    mut instructions: Vec<EditInstruction<&'a str>>,
+    unit: Unit,
+    reversed: bool,
+) -> Vec<EditInstruction<&'a str>> {
+    if reversed {
+        //work in string order
+        instructions.reverse();
+    }
+    //every instruction is replaced by one or more instructions
+    let mut slots: Vec<Vec<EditInstruction<&'a str>>> =
+        instructions.iter().map(|i| vec![i.clone()]).collect();
+    let mut names = CAPTURE_NAMES.iter();
+    for (i, instruction) in instructions.iter().enumerate() {
+        let t = match instruction {
+            EditInstruction::Insertion(t) => *t,
+            _ => continue,
+        };
+        //the longest identity that occurs in the insertion
+        let mut copied: Option<(usize, &str, usize)> = None;
+        for (j, slot) in slots.iter().enumerate() {
+            let s = match slot.as_slice() {
+                [EditInstruction::Identity(s)] => *s,
+                [EditInstruction::Captured(_, captured)] => match **captured {
+                    EditInstruction::Identity(s) => s,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            if unit.count(s) < 2 || copied.is_some_and(|(_, c, _)| c.len() >= s.len()) {
+                continue;
+            }
+            if let Some(offset) = t.match_indices(s).map(|(offset, _)| offset).find(|offset| {
+                unit.is_boundary(t, *offset) && unit.is_boundary(t, offset + s.len())
+            }) {
+                copied = Some((j, s, offset));
+            }
+        }
+        if let Some((j, s, offset)) = copied {
+            let name = match slots[j].as_slice() {
+                [EditInstruction::Captured(name, _)] => *name,
+                _ => match names.next() {
+                    Some(name) => *name,
+                    None => break,
+                },
+            };
+            slots[j] = vec![EditInstruction::Captured(
+                name,
+                Box::new(EditInstruction::Identity(s)),
+            )];
+            let mut parts = Vec::with_capacity(3);
+            if offset > 0 {
+                parts.push(EditInstruction::Insertion(&t[..offset]));
+            }
+            parts.push(EditInstruction::BackReference(name));
+            if offset + s.len() < t.len() {
+                parts.push(EditInstruction::Insertion(&t[offset + s.len()..]));
+            }
+            slots[i] = parts;
+            continue;
+        }
+        //an insertion that repeats adjacent material
+        let before = i
+            .checked_sub(1)
+            .map(|j| (j, slots[j].as_slice()))
+            .and_then(|(j, slot)| match slot {
+                [EditInstruction::Identity(s)] if s.ends_with(t) => {
+                    Some((j, *s, s.len() - t.len()))
                 }
+                _ => None,
+            });
+        let after = slots.get(i + 1).and_then(|slot| match slot.as_slice() {
+            [EditInstruction::Identity(s)] if s.starts_with(t) => Some((i + 1, *s, 0)),
+            _ => None,
+        });
+        if let Some((j, s, offset)) = before.or(after) {
+            if !unit.is_boundary(s, offset) || !unit.is_boundary(s, offset + t.len()) {
+                continue;
             }
-            head += tail.as_str();
-            Ok(head)
+            let name = match names.next() {
+                Some(name) => *name,
+                None => break,
+            };
+            let mut parts = Vec::with_capacity(3);
+            if offset > 0 {
+                parts.push(EditInstruction::Identity(&s[..offset]));
+            }
+            parts.push(EditInstruction::Captured(
+                name,
+                Box::new(EditInstruction::Identity(&s[offset..offset + t.len()])),
+            ));
+            if offset + t.len() < s.len() {
+                parts.push(EditInstruction::Identity(&s[offset + t.len()..]));
+            }
+            slots[j] = parts;
+            slots[i] = vec![EditInstruction::BackReference(name)];
+        }
+    }
+    let mut instructions: Vec<EditInstruction<&'a str>> = slots.into_iter().flatten().collect();
+    if reversed {
+        instructions.reverse();
+    }
+    instructions
+}
+
+/// Keeps at most `context` units of every identity literally, on the side(s) where it borders a
+/// change, the rest of the identity is abstracted to its length
+fn restrict_context(
+    instructions: Vec<EditInstruction<&str>>,
+    context: usize,
+    unit: Unit,
+    reversed: bool,
+) -> Vec<EditInstruction<&str>> {
+    let is_change = |instruction: Option<&EditInstruction<&str>>| match instruction {
+        Some(EditInstruction::Identity(_))
+        | Some(EditInstruction::GenericIdentity(_))
+        | Some(EditInstruction::WildcardIdentity)
+        | Some(EditInstruction::Captured(..)) => false,
+        Some(_) => true,
+        None => false,
+    };
+    let mut result = Vec::with_capacity(instructions.len());
+    for (i, instruction) in instructions.iter().enumerate() {
+        let s = match instruction {
+            EditInstruction::Identity(s) => *s,
+            EditInstruction::Captured(..) => {
+                //captured material is copied rather than checked, so it is abstracted entirely
+                let mut instruction = instruction.clone();
+                abstract_identity(&mut instruction, unit);
+                result.push(instruction);
+                continue;
+            }
+            instruction => {
+                result.push(instruction.clone());
+                continue;
+            }
+        };
+        //context kept on the side of the previous and the next instruction
+        let previous = if i > 0 && is_change(instructions.get(i - 1)) {
+            context
         } else {
-            /////////////////////////////////// NORMAL or PREFIX MODE
+            0
+        };
+        let next = if is_change(instructions.get(i + 1)) {
+            context
+        } else {
+            0
+        };
+        let length = unit.count(s);
+        if previous + next >= length {
+            result.push(EditInstruction::Identity(s));
+            continue;
+        }
+        //in suffix mode, the previous instruction is at the end of the string
+        let (head, tail) = if reversed {
+            (next, previous)
+        } else {
+            (previous, next)
+        };
+        let headend = unit.forward(s, 0, head).expect("within length");
+        let tailbegin = unit.backward(s, s.len(), tail).expect("within length");
+        let mut parts = Vec::with_capacity(3);
+        if head > 0 {
+            parts.push(EditInstruction::Identity(&s[..headend]));
+        }
+        parts.push(EditInstruction::GenericIdentity(
+            (length - head - tail) as u32,
+        ));
+        if tail > 0 {
+            parts.push(EditInstruction::Identity(&s[tailbegin..]));
+        }
+        if reversed {
+            parts.reverse();
+        }
+        result.extend(parts);
+    }
+    result
+}
 
-            //we use Options because we want to defer making clones and new instances until we
-            //really need to
-            let mut tail: Option<String> = None;
-            let mut head: Option<String> = None;
+/// Checks whether the operation, possibly along with the next ones, amounts to the transposition of
+/// two adjacent units. Returns the source range of the two units and the number of subsequent
+/// operations that are covered as well.
+fn transposition(
+    source: &str,
+    target: &str,
+    op: &DiffOp,
+    next: &[DiffOp],
+    unit: Unit,
+) -> Option<(Range<usize>, usize)> {
+    let is_unit = |s: &str| unit.count(s) == 1;
+    match (op, next) {
+        //two units deleted and inserted in reverse order: -[ab]+[ba]
+        (DiffOp::Delete(s), [DiffOp::Insert(t), ..]) => {
+            let deleted = unit.split(&source[s.clone()]);
+            let inserted = unit.split(&target[t.clone()]);
+            if deleted.len() == 2
+                && inserted.len() == 2
+                && deleted[0] == inserted[1]
+                && deleted[1] == inserted[0]
+                && deleted[0] != deleted[1]
+            {
+                Some((s.clone(), 1))
+            } else {
+                None
+            }
+        }
+        //a unit that moves across a single other unit: -[a]=[b]+[a] or +[a]=[b]-[a]
+        (DiffOp::Delete(s), [DiffOp::Equal(es, et), DiffOp::Insert(t)])
+        | (DiffOp::Insert(t), [DiffOp::Equal(es, et), DiffOp::Delete(s)]) => {
+            if is_unit(&source[s.clone()])
+                && is_unit(&source[es.clone()])
+                && source[s.clone()] == target[t.clone()]
+                && source[es.clone()] == target[et.clone()]
+            {
+                Some((s.start.min(es.start)..s.end.max(es.end), 2))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
 
-            let mut matches = false;
+/// Adds the instructions for a stretch of source and target that is equal except for letter case
+fn push_case_changes<'a>(
+    instructions: &mut Vec<EditInstruction<&'a str>>,
+    source: &'a str,
+    target: &'a str,
+    options: &DiffOptions,
+    prev: &mut isize,
+) {
+    #[derive(Copy, Clone, PartialEq)]
+    enum Case {
+        Same,
+        Upper,
+        Lower,
+        Other,
+    }
 
-            for instruction in self.instructions.iter() {
-                let tailchars = if let Some(tail) = tail.as_ref() {
-                    tail.chars().count()
-                } else {
-                    input.chars().count()
-                };
-                /*eprintln!("DEBUG: Instruction: {}", instruction);
-                eprintln!("              Head: {}", head);
-                eprintln!("              Tail: {}", tail);*/
-                match instruction {
-                    EditInstruction::Deletion(prefix) => {
-                        match instruction_applies(prefix, input, &tail, tailchars) {
-                            Ok(matchchars) => {
-                                matches = true;
-                                if tail.is_none() {
-                                    tail = Some(input.to_string())
-                                }; //clone
-                                tail.as_mut().map(|t| t.drain(..matchchars));
-                            }
-                            Err(e) => return Err(e),
-                        }
+    let unit = options.unit;
+    let mut runs: Vec<(Case, Range<usize>, Range<usize>)> = Vec::new();
+    let (mut sourcepos, mut targetpos) = (0, 0);
+    for (s, t) in unit.split(source).into_iter().zip(unit.split(target)) {
+        let case = if s == t {
+            Case::Same
+        } else if t == s.to_uppercase() {
+            Case::Upper
+        } else if t == s.to_lowercase() {
+            Case::Lower
+        } else {
+            Case::Other
+        };
+        match runs.last_mut() {
+            Some((prevcase, sourcerange, targetrange)) if *prevcase == case => {
+                sourcerange.end += s.len();
+                targetrange.end += t.len();
+            }
+            _ => runs.push((
+                case,
+                sourcepos..sourcepos + s.len(),
+                targetpos..targetpos + t.len(),
+            )),
+        }
+        sourcepos += s.len();
+        targetpos += t.len();
+    }
+    if options.mode == Mode::Suffix {
+        runs.reverse();
+    }
+
+    //target range of the identity instruction that was added last, if that was the last instruction
+    let mut identity: Option<Range<usize>> = None;
+    for (case, sourcerange, targetrange) in runs {
+        let t = &target[targetrange.clone()];
+        let length = unit.count(t);
+        match case {
+            Case::Upper => instructions.push(EditInstruction::UppercaseSpan(length as u32)),
+            Case::Lower => instructions.push(EditInstruction::LowercaseSpan(length as u32)),
+            _ => {}
+        }
+        if case == Case::Other {
+            if options.allow_substitutions {
+                instructions.push(EditInstruction::Substitution(&source[sourcerange], t));
+            } else {
+                instructions.push(EditInstruction::Deletion(&source[sourcerange]));
+                instructions.push(EditInstruction::Insertion(t));
+            }
+        } else {
+            let targetrange = match identity.take() {
+                Some(prevrange) if case == Case::Same => {
+                    //extend the previous identity rather than adding a new one
+                    instructions.pop();
+                    prevrange.start.min(targetrange.start)..prevrange.end.max(targetrange.end)
+                }
+                _ => targetrange,
+            };
+            instructions.push(EditInstruction::Identity(&target[targetrange.clone()]));
+            identity = Some(targetrange);
+            continue;
+        }
+        identity = None;
+    }
+    *prev = 0;
+}
+
+/// Shrinks equal stretches (operations in forward order) so they start and end on unit boundaries
+/// in both source and target, the material that is cut off becomes part of the adjacent changes.
+fn snap_to_boundaries(source: &str, target: &str, ops: Vec<DiffOp>, unit: Unit) -> Vec<DiffOp> {
+    let mut result = Vec::with_capacity(ops.len());
+    for op in ops {
+        if let DiffOp::Equal(s, t) = op {
+            let (mut sbegin, mut tbegin) = (s.start, t.start);
+            let (mut send, mut tend) = (s.end, t.end);
+            while sbegin < send
+                && !(unit.is_boundary(source, sbegin) && unit.is_boundary(target, tbegin))
+            {
+                let charlen = source[sbegin..].chars().next().map_or(1, |c| c.len_utf8());
+                sbegin += charlen;
+                tbegin += charlen;
+            }
+            while send > sbegin
+                && !(unit.is_boundary(source, send) && unit.is_boundary(target, tend))
+            {
+                let charlen = source[..send]
+                    .chars()
+                    .next_back()
+                    .map_or(1, |c| c.len_utf8());
+                send -= charlen;
+                tend -= charlen;
+            }
+            if sbegin > s.start {
+                result.push(DiffOp::Delete(s.start..sbegin));
+                result.push(DiffOp::Insert(t.start..tbegin));
+            }
+            if send > sbegin {
+                result.push(DiffOp::Equal(sbegin..send, tbegin..tend));
+            }
+            if s.end > send {
+                result.push(DiffOp::Delete(send..s.end));
+                result.push(DiffOp::Insert(tend..t.end));
+            }
+        } else {
+            result.push(op);
+        }
+    }
+    result
+}
+
+/// Merges every consecutive run of deletions and insertions into a single deletion followed by a
+/// single insertion
+fn merge_changes(ops: Vec<DiffOp>) -> Vec<DiffOp> {
+    fn union(range: &mut Option<Range<usize>>, other: Range<usize>) {
+        if other.is_empty() {
+            return;
+        }
+        *range = Some(match range.take() {
+            Some(range) => range.start.min(other.start)..range.end.max(other.end),
+            None => other,
+        });
+    }
+    fn flush(
+        result: &mut Vec<DiffOp>,
+        deleted: &mut Option<Range<usize>>,
+        inserted: &mut Option<Range<usize>>,
+    ) {
+        if let Some(s) = deleted.take() {
+            result.push(DiffOp::Delete(s));
+        }
+        if let Some(t) = inserted.take() {
+            result.push(DiffOp::Insert(t));
+        }
+    }
+
+    let mut result = Vec::with_capacity(ops.len());
+    let mut deleted = None;
+    let mut inserted = None;
+    for op in ops {
+        match op {
+            DiffOp::Delete(s) => union(&mut deleted, s),
+            DiffOp::Insert(t) => union(&mut inserted, t),
+            op => {
+                flush(&mut result, &mut deleted, &mut inserted);
+                result.push(op);
+            }
+        }
+    }
+    flush(&mut result, &mut deleted, &mut inserted);
+    result
+}
+
+/// Options for applying edit scripts with [`ApplyEditScript::apply_with()`]
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct ApplyOptions {
+    /// The mode to apply the edit script in, defaults to the mode of the edit script itself
+    pub mode: Option<Mode>,
+
+    /// The unit the edit script was computed on
+    pub unit: Unit,
+
+    /// Normalize the input and the contents of the edit script to this unicode normalization form
+    /// prior to applying it
+    pub normalization: Option<Normalization>,
+
+    /// The character classes that the edit script may refer to
+    pub classes: CharClasses,
+
+    /// How to choose between the alternatives of options that match at the same position
+    pub option_matching: OptionMatching,
+}
+
+pub trait ApplyEditScript {
+    /// Applies the edit script to the input string
+    fn apply_to(&self, input: &str, mode: Option<Mode>) -> Result<String, ApplyError> {
+        self.apply_with(
+            input,
+            &ApplyOptions {
+                mode,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Applies the edit script to the input string according to the specified options
+    fn apply_with(&self, input: &str, options: &ApplyOptions) -> Result<String, ApplyError>;
+
+    /// Applies the edit script to the input string according to the specified options, and
+    /// returns the output along with a trace of what every instruction consumed and produced
+    fn apply_traced(
+        &self,
+        input: &str,
+        options: &ApplyOptions,
+    ) -> Result<(String, Vec<TraceStep>), ApplyError>;
+
+    /// Applies the edit script to the input string according to the specified options, and
+    /// returns every distinct output it can produce (sorted): all alternatives of options and
+    /// classes are explored, in infix mode also all the ways to match non-overlapping occurrences
+    fn apply_all(&self, input: &str, options: &ApplyOptions) -> Result<Vec<String>, ApplyError>;
+}
+
+impl ApplyEditScript for EditScript<String> {
+    fn apply_with(&self, input: &str, options: &ApplyOptions) -> Result<String, ApplyError> {
+        self.as_ref().apply_with(input, options)
+    }
+
+    fn apply_traced(
+        &self,
+        input: &str,
+        options: &ApplyOptions,
+    ) -> Result<(String, Vec<TraceStep>), ApplyError> {
+        self.as_ref().apply_traced(input, options)
+    }
+
+    fn apply_all(&self, input: &str, options: &ApplyOptions) -> Result<Vec<String>, ApplyError> {
+        self.as_ref().apply_all(input, options)
+    }
+}
+
+impl ApplyEditScript for EditScript<&str> {
+    fn apply_with(&self, input: &str, options: &ApplyOptions) -> Result<String, ApplyError> {
+        apply_script(self, input, options, None)
+    }
+
+    fn apply_traced(
+        &self,
+        input: &str,
+        options: &ApplyOptions,
+    ) -> Result<(String, Vec<TraceStep>), ApplyError> {
+        let mut trace = Vec::new();
+        let output = apply_script(self, input, options, Some(&mut trace))?;
+        Ok((output, trace))
+    }
+
+    fn apply_all(&self, input: &str, options: &ApplyOptions) -> Result<Vec<String>, ApplyError> {
+        enumerate::apply_all(self, input, options)
+    }
+}
+
+/// Applies the edit script to the input string, fills the trace if one is passed
+fn apply_script(
+    editscript: &EditScript<&str>,
+    input: &str,
+    options: &ApplyOptions,
+    trace: Option<&mut Vec<TraceStep>>,
+) -> Result<String, ApplyError> {
+    if let Some(normalization) = options.normalization {
+        let editscript = EditScript {
+            mode: editscript.mode,
+            distance: editscript.distance,
+            instructions: editscript
+                .instructions
+                .iter()
+                .map(|instruction| instruction.map(|s| normalization.normalize(s)))
+                .collect(),
+        };
+        let options = ApplyOptions {
+            normalization: None,
+            ..options.clone()
+        };
+        return apply_script(
+            &editscript.as_ref(),
+            &normalization.normalize(input),
+            &options,
+            trace,
+        );
+    }
+    let mode = options.mode.unwrap_or(editscript.mode);
+    let unit = options.unit;
+    let instructions = &editscript.instructions;
+    let mut steps = trace.as_ref().map(|_| Vec::new());
+    let (output, input) = match mode {
+        Mode::Infix => {
+            //iterate over the input attempting to match at each unit boundary, the script
+            //may apply multiple times
+            let mut input: Cow<str> = Cow::Borrowed(input);
+            let mut result = String::new();
+            let mut matches = false;
+            //the error at the first position is reported if the script matches nowhere
+            let mut error = None;
+            let mut begin = 0;
+            let mut pos = 0;
+            if instructions.is_empty() {
+                return Err(ApplyError::EmptyScript);
+            }
+            //an empty input is attempted once as well
+            while pos < input.len() || (input.is_empty() && error.is_none() && !matches) {
+                let mut attempt = steps.as_ref().map(|_| Vec::new());
+                match apply_forward(instructions, &input[pos..], options, &mut attempt) {
+                    Err(err) if err.is_permanent() => return Err(err),
+                    Err(err) => {
+                        error.get_or_insert_with(|| err.shifted(0, input[..pos].chars().count()));
                     }
-                    EditInstruction::Insertion(s) => {
-                        if head.is_none() {
-                            head = Some(String::new())
-                        }; //init
-                        head.as_mut().map(|h| *h += s);
+                    Ok((output, consumed, remainder)) => {
                         matches = true;
-                    }
-                    EditInstruction::GenericIdentity(keeplength) => {
-                        let keeplength = *keeplength as usize;
-                        if keeplength > tailchars {
-                            return Err(ApplyError::WithMessage(format!("Edit script does not match current word, length to keep is longer than head")));
+                        result += &input[begin..pos];
+                        if let (Some(steps), Some(attempt)) = (steps.as_mut(), attempt) {
+                            let target = result.len();
+                            steps.extend(
+                                attempt.into_iter().map(|step| step.shifted(0, pos, target)),
+                            );
                         }
-                        if head.is_none() {
-                            head = Some(String::new())
-                        }; //init
-                        if tail.is_none() {
-                            tail = Some(input.to_string())
-                        }; //clone
-                        if let (Some(head), Some(tail)) = (head.as_mut(), tail.as_mut()) {
-                            head.extend(tail.drain(..keeplength));
-                            matches = true;
-                        } else {
-                            panic!(
-                                "Can't unpack head and tail for EditInstruction::GenericIdentity"
-                            )
-                        } //should never happen
-                    }
-                    EditInstruction::Identity(prefix) => {
-                        match instruction_applies(prefix, input, &tail, tailchars) {
-                            Ok(matchchars) => {
-                                if head.is_none() {
-                                    head = Some(String::new())
-                                }; //init
-                                if tail.is_none() {
-                                    tail = Some(input.to_string())
-                                }; //clone
-                                if let (Some(head), Some(tail)) = (head.as_mut(), tail.as_mut()) {
-                                    head.extend(tail.drain(..matchchars));
-                                }
-                                matches = true;
-                            }
-                            Err(e) => return Err(e),
+                        result += output.as_str();
+                        if let Cow::Owned(remainder) = remainder {
+                            //case transformations changed the rest of the input
+                            input = Cow::Owned(input[..pos].to_string() + remainder.as_str());
                         }
+                        begin = pos + consumed;
+                        if consumed > 0 {
+                            pos = begin;
+                            continue;
+                        }
+                    }
+                }
+                pos = unit.forward(&input, pos, 1).unwrap_or(input.len());
+            }
+            match error {
+                Some(err) if !matches => return Err(err),
+                _ => {
+                    result += &input[begin..];
+                    (result, input)
+                }
+            }
+        }
+        Mode::Suffix => {
+            let (tail, end, input) = apply_backward(instructions, input, options, &mut steps)?;
+            (input[..end].to_string() + tail.as_str(), input)
+        }
+        Mode::Prefix => {
+            //the unchanged remainder (common suffix) is not part of the script
+            let (head, consumed, input) = apply_forward(instructions, input, options, &mut steps)?;
+            let output = head + &input[consumed..];
+            (output, input)
+        }
+        Mode::Normal => {
+            if instructions.is_empty() {
+                return Err(ApplyError::EmptyScript);
+            }
+            let (head, _, input) = apply_forward(instructions, input, options, &mut steps)?;
+            (head, input)
+        }
+    };
+    if let (Some(trace), Some(steps)) = (trace, steps) {
+        *trace = trace::finish(steps, instructions, &input, &output, mode == Mode::Suffix);
+    }
+    Ok(output)
+}
+
+/// Returns the byte offset where `s` ends if it occurs at the given offset in the input
+fn match_forward(input: &str, offset: usize, s: &str, unit: Unit) -> Option<usize> {
+    if input[offset..].starts_with(s) && unit.is_boundary(input, offset + s.len()) {
+        Some(offset + s.len())
+    } else {
+        None
+    }
+}
+
+/// Returns the byte offset where `s` begins if it occurs right before the given offset in the input
+fn match_backward(input: &str, offset: usize, s: &str, unit: Unit) -> Option<usize> {
+    if input[..offset].ends_with(s) && unit.is_boundary(input, offset - s.len()) {
+        Some(offset - s.len())
+    } else {
+        None
+    }
+}
+
+/// Returns the byte offset where one of the alternatives ends if it occurs at the given offset in
+/// the input. If several alternatives match, they are tried in order of preference and the first
+/// after which the remaining instructions (`rest`) apply as well is chosen.
+fn match_options_forward(
+    input: &str,
+    offset: usize,
+    alternatives: &[&str],
+    rest: &[EditInstruction<&str>],
+    capture: Option<&str>,
+    options: &ApplyOptions,
+    captures: &Captures,
+) -> Option<usize> {
+    let lengths = alternatives.iter().filter_map(|alternative| {
+        match_forward(input, offset, alternative, options.unit).map(|end| end - offset)
+    });
+    let rest_applies = |length| {
+        let mut attempt = captures.clone();
+        if let Some(name) = capture {
+            attempt.insert(name, &input[offset..offset + length]);
+        }
+        let remainder = &input[offset + length..];
+        apply_forward_pass(rest, remainder, options, &mut attempt, &mut None).is_ok()
+    };
+    let length = options.option_matching.choose(lengths, rest_applies)?;
+    Some(offset + length)
+}
+
+/// Returns the byte offset where one of the alternatives begins if it occurs right before the
+/// given offset in the input. If several alternatives match, they are tried in order of
+/// preference and the first after which the remaining instructions (`rest`) apply as well is
+/// chosen.
+fn match_options_backward(
+    input: &str,
+    offset: usize,
+    alternatives: &[&str],
+    rest: &[EditInstruction<&str>],
+    capture: Option<&str>,
+    options: &ApplyOptions,
+    captures: &Captures,
+) -> Option<usize> {
+    let lengths = alternatives.iter().filter_map(|alternative| {
+        match_backward(input, offset, alternative, options.unit).map(|begin| offset - begin)
+    });
+    let rest_applies = |length| {
+        let mut attempt = captures.clone();
+        if let Some(name) = capture {
+            attempt.insert(name, &input[offset - length..offset]);
+        }
+        let remainder = &input[..offset - length];
+        apply_backward_pass(rest, remainder, options, &mut attempt, &mut None).is_ok()
+    };
+    let length = options.option_matching.choose(lengths, rest_applies)?;
+    Some(offset - length)
+}
+
+/// Returns the byte offset where a member of the class ends if it occurs at the given offset in
+/// the input, the longest member is preferred
+fn match_class_forward(
+    input: &str,
+    offset: usize,
+    members: &[String],
+    unit: Unit,
+) -> Option<usize> {
+    members
+        .iter()
+        .filter_map(|member| match_forward(input, offset, member, unit))
+        .max()
+}
+
+/// Returns the byte offset where a member of the class begins if it occurs right before the given
+/// offset in the input, the longest member is preferred
+fn match_class_backward(
+    input: &str,
+    offset: usize,
+    members: &[String],
+    unit: Unit,
+) -> Option<usize> {
+    members
+        .iter()
+        .filter_map(|member| match_backward(input, offset, member, unit))
+        .min()
+}
+
+/// Returns the members of the named class that the instruction at index `i` refers to
+fn class_members<'a>(
+    name: &str,
+    options: &'a ApplyOptions,
+    i: usize,
+) -> Result<&'a [String], ApplyError> {
+    options
+        .classes
+        .get(name)
+        .ok_or_else(|| ApplyError::UndefinedClass {
+            index: i,
+            name: name.to_string(),
+        })
+}
+
+/// Returns what an instruction requires of the input, in edit script notation, along with the
+/// number of units it covers
+fn expectation(instruction: &EditInstruction<&str>, unit: Unit) -> (String, usize) {
+    match instruction {
+        EditInstruction::Identity(s)
+        | EditInstruction::Deletion(s)
+        | EditInstruction::Substitution(s, _)
+        | EditInstruction::Transposition(s) => (s.to_string(), unit.count(s)),
+        EditInstruction::IdentityOptions(v) | EditInstruction::DeletionOptions(v) => (
+            v.join("|"),
+            v.iter().map(|s| unit.count(s)).max().unwrap_or(0),
+        ),
+        EditInstruction::IdentityClass(name) | EditInstruction::DeletionClass(name) => {
+            (format!("{{{}}}", name), 1)
+        }
+        EditInstruction::GenericIdentity(n)
+        | EditInstruction::LowercaseSpan(n)
+        | EditInstruction::UppercaseSpan(n) => (format!("#{}", n), *n as usize),
+        _ => (instruction.to_string(), 0),
+    }
+}
+
+/// Returns the error for an instruction (at index `i`) that does not match the input at the given
+/// byte offset, when applying forward
+fn mismatch_forward(
+    input: &str,
+    offset: usize,
+    instruction: &EditInstruction<&str>,
+    i: usize,
+    unit: Unit,
+) -> ApplyError {
+    let (expected, length) = expectation(instruction, unit);
+    let end = unit.forward(input, offset, length).unwrap_or(input.len());
+    ApplyError::NoMatch {
+        index: i,
+        position: input[..offset].chars().count(),
+        expected,
+        found: input[offset..end].to_string(),
+    }
+}
+
+/// Returns the error for an instruction (at index `i`) that does not match the input right before
+/// the given byte offset, when applying backward
+fn mismatch_backward(
+    input: &str,
+    offset: usize,
+    instruction: &EditInstruction<&str>,
+    i: usize,
+    unit: Unit,
+) -> ApplyError {
+    let (expected, length) = expectation(instruction, unit);
+    let begin = unit.backward(input, offset, length).unwrap_or(0);
+    ApplyError::NoMatch {
+        index: i,
+        position: input[..begin].chars().count(),
+        expected,
+        found: input[begin..offset].to_string(),
+    }
+}
+
+fn nested_capture(i: usize) -> ApplyError {
+    ApplyError::Unsupported {
+        index: i,
+        reason: "captures can not be nested".to_string(),
+    }
+}
+
+/// Converts the part of the input between the two byte offsets to lowercase or uppercase
+fn change_case(input: &str, begin: usize, end: usize, uppercase: bool) -> String {
+    let mut result = String::with_capacity(input.len());
+    result += &input[..begin];
+    if uppercase {
+        result += input[begin..end].to_uppercase().as_str();
+    } else {
+        result += input[begin..end].to_lowercase().as_str();
+    }
+    result += &input[end..];
+    result
+}
+
+/// Returns the two units of a transposition (the instruction at index `i`) in swapped order
+fn swap_units(s: &str, unit: Unit, i: usize) -> Result<String, ApplyError> {
+    match unit.split(s).as_slice() {
+        [first, second] => Ok(second.to_string() + first),
+        _ => Err(ApplyError::Unsupported {
+            index: i,
+            reason: format!("a transposition takes exactly two units, got '{}'", s),
+        }),
+    }
+}
+
+/// Material captured by named identities while applying an edit script
+#[derive(Clone, Debug, Default)]
+struct Captures {
+    /// Captures from a previous pass, so back-references can refer to material that is only
+    /// captured later on
+    previous: HashMap<String, String>,
+    /// Captures made in this pass
+    current: HashMap<String, String>,
+    /// Is this the final pass, in which all back-references must be resolved?
+    final_pass: bool,
+    /// Set if a back-reference could not be resolved in this pass
+    unresolved: bool,
+}
+
+impl Captures {
+    fn insert(&mut self, name: &str, s: &str) {
+        self.current.insert(name.to_string(), s.to_string());
+    }
+
+    /// Returns the captured material for a back-reference (the instruction at index `i`), an empty
+    /// string if it is not captured yet and will be resolved in the final pass
+    fn resolve(&mut self, name: &str, i: usize) -> Result<String, ApplyError> {
+        if let Some(s) = self.current.get(name).or_else(|| self.previous.get(name)) {
+            Ok(s.clone())
+        } else if self.final_pass {
+            Err(ApplyError::UnknownCapture {
+                index: i,
+                name: name.to_string(),
+            })
+        } else {
+            self.unresolved = true;
+            Ok(String::new())
+        }
+    }
+
+    /// Prepares the final pass, in which back-references are resolved with what this pass
+    /// captured
+    fn into_final(self) -> Self {
+        Captures {
+            previous: self.current,
+            final_pass: true,
+            ..Default::default()
+        }
+    }
+}
+
+///auxiliary internal function for apply_with() in normal/prefix/infix mode, applies the
+///instructions from the start of the input and returns the output along with the byte offset up
+///to which the input was consumed, and the input itself as changed by any case transformations
+fn apply_forward<'a>(
+    instructions: &[EditInstruction<&str>],
+    input: &'a str,
+    options: &ApplyOptions,
+    trace: &mut Option<Vec<Step>>,
+) -> Result<(String, usize, Cow<'a, str>), ApplyError> {
+    let mut captures = Captures::default();
+    let result = apply_forward_pass(instructions, input, options, &mut captures, trace)?;
+    if !captures.unresolved {
+        return Ok(result);
+    }
+    //back-references to material that is captured later on, now it is known
+    if let Some(steps) = trace {
+        steps.clear();
+    }
+    apply_forward_pass(
+        instructions,
+        input,
+        options,
+        &mut captures.into_final(),
+        trace,
+    )
+}
+
+fn apply_forward_pass<'a>(
+    instructions: &[EditInstruction<&str>],
+    input: &'a str,
+    options: &ApplyOptions,
+    captures: &mut Captures,
+    trace: &mut Option<Vec<Step>>,
+) -> Result<(String, usize, Cow<'a, str>), ApplyError> {
+    let unit = options.unit;
+    let mut input: Cow<str> = Cow::Borrowed(input);
+    let mut head = String::new();
+    let mut pos = 0;
+    for (i, instruction) in instructions.iter().enumerate() {
+        //the material that a named identity matches is captured
+        let (instruction, capture) = match instruction {
+            EditInstruction::Captured(name, instruction) => (&**instruction, Some(*name)),
+            instruction => (instruction, None),
+        };
+        let begin = pos;
+        let outstart = head.len();
+        let mismatch = |input: &str| mismatch_forward(input, pos, instruction, i, unit);
+        match instruction {
+            EditInstruction::Deletion(prefix) => {
+                pos = match_forward(&input, pos, prefix, unit).ok_or_else(|| mismatch(&input))?;
+            }
+            EditInstruction::Insertion(s) => {
+                head += s;
+            }
+            EditInstruction::GenericIdentity(keeplength) => {
+                let end = unit
+                    .forward(&input, pos, *keeplength as usize)
+                    .ok_or_else(|| mismatch(&input))?;
+                head += &input[pos..end];
+                pos = end;
+            }
+            EditInstruction::Identity(prefix) => {
+                let end =
+                    match_forward(&input, pos, prefix, unit).ok_or_else(|| mismatch(&input))?;
+                head += &input[pos..end];
+                pos = end;
+            }
+            EditInstruction::WildcardIdentity => {
+                //try the longest match first, until the remaining instructions apply
+                let mut end = input.len();
+                loop {
+                    let mut attempt = captures.clone();
+                    if let Some(name) = capture {
+                        attempt.insert(name, &input[pos..end]);
                     }
-                    EditInstruction::IdentityOptions(prefixes) => {
-                        matches = false;
-                        for prefix in prefixes {
-                            if let Ok(matchchars) =
-                                instruction_applies(prefix, input, &tail, tailchars)
+                    let mut attempttrace = trace.as_ref().map(|_| Vec::new());
+                    match apply_forward_pass(
+                        &instructions[i + 1..],
+                        &input[end..],
+                        options,
+                        &mut attempt,
+                        &mut attempttrace,
+                    ) {
+                        Ok((output, consumed, remainder)) => {
+                            *captures = attempt;
+                            if let (Some(steps), Some(attempttrace)) =
+                                (trace.as_mut(), attempttrace)
                             {
-                                if head.is_none() {
-                                    head = Some(String::new())
-                                }; //init
-                                if tail.is_none() {
-                                    tail = Some(input.to_string())
-                                }; //clone
-                                if let (Some(head), Some(tail)) = (head.as_mut(), tail.as_mut()) {
-                                    head.extend(tail.drain(..matchchars));
-                                }
-                                matches = true;
-                                break;
+                                let target = outstart + end - pos;
+                                steps.push(Step {
+                                    index: i,
+                                    source: pos..end,
+                                    target: outstart..target,
+                                });
+                                steps.extend(
+                                    attempttrace
+                                        .into_iter()
+                                        .map(|step| step.shifted(i + 1, end, target)),
+                                );
+                            }
+                            let remainder = match remainder {
+                                Cow::Owned(remainder) => Some(remainder),
+                                Cow::Borrowed(_) => None,
+                            };
+                            head += &input[pos..end];
+                            head += output.as_str();
+                            if let Some(remainder) = remainder {
+                                input = Cow::Owned(input[..end].to_string() + remainder.as_str());
                             }
+                            return Ok((head, end + consumed, input));
                         }
-                        if !matches {
-                            return Err(ApplyError::NoMatch);
+                        Err(err) if end == pos || err.is_permanent() => {
+                            return Err(err.shifted(i + 1, input[..end].chars().count()))
                         }
+                        Err(_) => end = unit.backward(&input, end, 1).unwrap_or(pos).max(pos),
+                    }
+                }
+            }
+            EditInstruction::IdentityOptions(prefixes) => {
+                let rest = &instructions[i + 1..];
+                let end =
+                    match_options_forward(&input, pos, prefixes, rest, capture, options, captures)
+                        .ok_or_else(|| mismatch(&input))?;
+                head += &input[pos..end];
+                pos = end;
+            }
+            EditInstruction::DeletionOptions(prefixes) => {
+                let rest = &instructions[i + 1..];
+                pos =
+                    match_options_forward(&input, pos, prefixes, rest, capture, options, captures)
+                        .ok_or_else(|| mismatch(&input))?;
+            }
+            EditInstruction::IdentityClass(name) => {
+                let members = class_members(name, options, i)?;
+                let end = match_class_forward(&input, pos, members, unit)
+                    .ok_or_else(|| mismatch(&input))?;
+                head += &input[pos..end];
+                pos = end;
+            }
+            EditInstruction::DeletionClass(name) => {
+                let members = class_members(name, options, i)?;
+                pos = match_class_forward(&input, pos, members, unit)
+                    .ok_or_else(|| mismatch(&input))?;
+            }
+            EditInstruction::BackReference(name) => {
+                head += captures.resolve(name, i)?.as_str();
+            }
+            EditInstruction::Captured(..) => return Err(nested_capture(i)),
+            EditInstruction::InsertionOptions(_) => {
+                return Err(ApplyError::Ambiguous { index: i });
+            }
+            EditInstruction::Substitution(prefix, replacement) => {
+                pos = match_forward(&input, pos, prefix, unit).ok_or_else(|| mismatch(&input))?;
+                head += replacement;
+            }
+            EditInstruction::Transposition(prefix) => {
+                let swapped = swap_units(prefix, unit, i)?;
+                pos = match_forward(&input, pos, prefix, unit).ok_or_else(|| mismatch(&input))?;
+                head += swapped.as_str();
+            }
+            EditInstruction::LowercaseAll | EditInstruction::UppercaseAll => {
+                let uppercase = *instruction == EditInstruction::UppercaseAll;
+                input = Cow::Owned(change_case(&input, pos, input.len(), uppercase));
+            }
+            EditInstruction::LowercaseSpan(length) | EditInstruction::UppercaseSpan(length) => {
+                let uppercase = matches!(instruction, EditInstruction::UppercaseSpan(_));
+                let end = unit
+                    .forward(&input, pos, *length as usize)
+                    .ok_or_else(|| mismatch(&input))?;
+                input = Cow::Owned(change_case(&input, pos, end, uppercase));
+            }
+        }
+        if let Some(name) = capture {
+            captures.insert(name, &input[begin..pos]);
+        }
+        if let Some(steps) = trace {
+            steps.push(Step {
+                index: i,
+                source: begin..pos,
+                target: outstart..head.len(),
+            });
+        }
+    }
+    Ok((head, pos, input))
+}
+
+///auxiliary internal function for apply_with() in suffix mode, applies the instructions from the
+///end of the input backwards and returns the output tail along with the byte offset where the
+///unconsumed head of the input ends
+fn apply_backward<'a>(
+    instructions: &[EditInstruction<&str>],
+    input: &'a str,
+    options: &ApplyOptions,
+    trace: &mut Option<Vec<Step>>,
+) -> Result<(String, usize, Cow<'a, str>), ApplyError> {
+    let mut captures = Captures::default();
+    let result = apply_backward_pass(instructions, input, options, &mut captures, trace)?;
+    if !captures.unresolved {
+        return Ok(result);
+    }
+    //back-references to material that is captured later on, now it is known
+    if let Some(steps) = trace {
+        steps.clear();
+    }
+    apply_backward_pass(
+        instructions,
+        input,
+        options,
+        &mut captures.into_final(),
+        trace,
+    )
+}
+
+fn apply_backward_pass<'a>(
+    instructions: &[EditInstruction<&str>],
+    input: &'a str,
+    options: &ApplyOptions,
+    captures: &mut Captures,
+    trace: &mut Option<Vec<Step>>,
+) -> Result<(String, usize, Cow<'a, str>), ApplyError> {
+    let unit = options.unit;
+    let mut input: Cow<str> = Cow::Borrowed(input);
+    let mut tail = String::new();
+    let mut end = input.len();
+    for (i, instruction) in instructions.iter().enumerate() {
+        //the material that a named identity matches is captured
+        let (instruction, capture) = match instruction {
+            EditInstruction::Captured(name, instruction) => (&**instruction, Some(*name)),
+            instruction => (instruction, None),
+        };
+        //measured from the end of the input, which case transformations do not affect
+        let start = input.len() - end;
+        let tailstart = tail.len();
+        let mismatch = |input: &str| mismatch_backward(input, end, instruction, i, unit);
+        match instruction {
+            EditInstruction::Deletion(suffix) => {
+                end = match_backward(&input, end, suffix, unit).ok_or_else(|| mismatch(&input))?;
+            }
+            EditInstruction::Insertion(s) => {
+                tail.insert_str(0, s);
+            }
+            EditInstruction::GenericIdentity(keeplength) => {
+                let begin = unit
+                    .backward(&input, end, *keeplength as usize)
+                    .ok_or_else(|| mismatch(&input))?;
+                tail.insert_str(0, &input[begin..end]);
+                end = begin;
+            }
+            EditInstruction::Identity(suffix) => {
+                let begin =
+                    match_backward(&input, end, suffix, unit).ok_or_else(|| mismatch(&input))?;
+                tail.insert_str(0, &input[begin..end]);
+                end = begin;
+            }
+            EditInstruction::WildcardIdentity => {
+                //try the longest match first, until the remaining instructions apply
+                let mut begin = 0;
+                loop {
+                    let mut attempt = captures.clone();
+                    if let Some(name) = capture {
+                        attempt.insert(name, &input[begin..end]);
                     }
-                    EditInstruction::DeletionOptions(prefixes) => {
-                        matches = false;
-                        for prefix in prefixes {
-                            if let Ok(matchchars) =
-                                instruction_applies(prefix, input, &tail, tailchars)
+                    let mut attempttrace = trace.as_ref().map(|_| Vec::new());
+                    match apply_backward_pass(
+                        &instructions[i + 1..],
+                        &input[..begin],
+                        options,
+                        &mut attempt,
+                        &mut attempttrace,
+                    ) {
+                        Ok((output, headend, remainder)) => {
+                            *captures = attempt;
+                            if let (Some(steps), Some(attempttrace)) =
+                                (trace.as_mut(), attempttrace)
                             {
-                                if tail.is_none() {
-                                    tail = Some(input.to_string())
-                                }; //clone
-                                tail.as_mut().map(|t| t.drain(..matchchars));
-                                matches = true;
-                                break;
+                                let target = tailstart + end - begin;
+                                steps.push(Step {
+                                    index: i,
+                                    source: start..input.len() - begin,
+                                    target: tailstart..target,
+                                });
+                                let source = input.len() - begin;
+                                steps.extend(
+                                    attempttrace
+                                        .into_iter()
+                                        .map(|step| step.shifted(i + 1, source, target)),
+                                );
+                            }
+                            let remainder = match remainder {
+                                Cow::Owned(remainder) => Some(remainder),
+                                Cow::Borrowed(_) => None,
+                            };
+                            tail.insert_str(0, &input[begin..end]);
+                            tail.insert_str(0, output.as_str());
+                            if let Some(remainder) = remainder {
+                                input = Cow::Owned(remainder + &input[begin..]);
                             }
+                            return Ok((tail, headend, input));
                         }
-                        if !matches {
-                            return Err(ApplyError::NoMatch);
+                        Err(err) if begin == end || err.is_permanent() => {
+                            return Err(err.shifted(i + 1, 0))
                         }
-                    }
-                    EditInstruction::InsertionOptions(_) => {
-                        return Err(ApplyError::WithMessage(format!("Edit script has multiple insertion options and is therefor ambiguous, unable to apply")));
+                        Err(_) => begin = unit.forward(&input, begin, 1).unwrap_or(end).min(end),
                     }
                 }
             }
-            if let Some(head) = head {
-                Ok(head)
-            } else if matches {
-                Ok(String::new())
-            } else {
-                Err(ApplyError::NoMatch)
+            EditInstruction::IdentityOptions(suffixes) => {
+                let rest = &instructions[i + 1..];
+                let begin =
+                    match_options_backward(&input, end, suffixes, rest, capture, options, captures)
+                        .ok_or_else(|| mismatch(&input))?;
+                tail.insert_str(0, &input[begin..end]);
+                end = begin;
+            }
+            EditInstruction::DeletionOptions(suffixes) => {
+                let rest = &instructions[i + 1..];
+                end =
+                    match_options_backward(&input, end, suffixes, rest, capture, options, captures)
+                        .ok_or_else(|| mismatch(&input))?;
             }
+            EditInstruction::IdentityClass(name) => {
+                let members = class_members(name, options, i)?;
+                let begin = match_class_backward(&input, end, members, unit)
+                    .ok_or_else(|| mismatch(&input))?;
+                tail.insert_str(0, &input[begin..end]);
+                end = begin;
+            }
+            EditInstruction::DeletionClass(name) => {
+                let members = class_members(name, options, i)?;
+                end = match_class_backward(&input, end, members, unit)
+                    .ok_or_else(|| mismatch(&input))?;
+            }
+            EditInstruction::BackReference(name) => {
+                tail.insert_str(0, captures.resolve(name, i)?.as_str());
+            }
+            EditInstruction::Captured(..) => return Err(nested_capture(i)),
+            EditInstruction::InsertionOptions(_) => {
+                return Err(ApplyError::Ambiguous { index: i });
+            }
+            EditInstruction::Substitution(suffix, replacement) => {
+                end = match_backward(&input, end, suffix, unit).ok_or_else(|| mismatch(&input))?;
+                tail.insert_str(0, replacement);
+            }
+            EditInstruction::Transposition(suffix) => {
+                let swapped = swap_units(suffix, unit, i)?;
+                end = match_backward(&input, end, suffix, unit).ok_or_else(|| mismatch(&input))?;
+                tail.insert_str(0, swapped.as_str());
+            }
+            EditInstruction::LowercaseAll | EditInstruction::UppercaseAll => {
+                let uppercase = *instruction == EditInstruction::UppercaseAll;
+                let changed = change_case(&input, 0, end, uppercase);
+                end = changed.len() - (input.len() - end);
+                input = Cow::Owned(changed);
+            }
+            EditInstruction::LowercaseSpan(length) | EditInstruction::UppercaseSpan(length) => {
+                let uppercase = matches!(instruction, EditInstruction::UppercaseSpan(_));
+                let begin = unit
+                    .backward(&input, end, *length as usize)
+                    .ok_or_else(|| mismatch(&input))?;
+                let changed = change_case(&input, begin, end, uppercase);
+                end = changed.len() - (input.len() - end);
+                input = Cow::Owned(changed);
+            }
+        }
+        if let Some(name) = capture {
+            captures.insert(name, &input[end..input.len() - start]);
+        }
+        if let Some(steps) = trace {
+            steps.push(Step {
+                index: i,
+                source: start..input.len() - end,
+                target: tailstart..tail.len(),
+            });
         }
     }
+    Ok((tail, end, input))
 }
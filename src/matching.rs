@@ -0,0 +1,295 @@
+//! Checks whether an edit script applies to an input, without building the output.
+
+use std::ops::Range;
+
+use crate::{
+    apply_backward, apply_forward, match_backward, match_class_backward, match_class_forward,
+    match_forward, ApplyOptions, EditInstruction, EditScript, Mode,
+};
+
+impl<T: AsRef<str>> EditScript<T> {
+    /// Tests whether the edit script applies to the input, see [`EditScript::matches_with()`]
+    pub fn matches(&self, input: &str, mode: Option<Mode>) -> Option<Range<usize>> {
+        self.matches_with(
+            input,
+            &ApplyOptions {
+                mode,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Tests whether the edit script applies to the input, using the same matching logic as
+    /// [`crate::ApplyEditScript::apply_with()`] but without building the output. Returns the span
+    /// of the input that the edit script covers (in byte offsets), in infix mode that is its first
+    /// occurrence. Nothing is allocated, except when the input has to be normalized (the span
+    /// then refers to the normalized input) or when the edit script contains case
+    /// transformations, which change the input that later instructions match against.
+    pub fn matches_with(&self, input: &str, options: &ApplyOptions) -> Option<Range<usize>> {
+        if let Some(normalization) = options.normalization {
+            let editscript = EditScript {
+                mode: self.mode,
+                distance: self.distance,
+                instructions: self
+                    .instructions
+                    .iter()
+                    .map(|instruction| instruction.map(|s| normalization.normalize(s.as_ref())))
+                    .collect(),
+            };
+            let options = ApplyOptions {
+                normalization: None,
+                ..options.clone()
+            };
+            return editscript.matches_with(&normalization.normalize(input), &options);
+        }
+        let instructions = self.instructions.as_slice();
+        let mode = options.mode.unwrap_or(self.mode);
+        if !instructions.iter().all(|instruction| {
+            is_applicable(instruction, instructions) && !is_case_change(instruction)
+        }) {
+            if instructions.iter().any(is_case_change) {
+                return self.matches_by_applying(input, options);
+            }
+            return None;
+        }
+        match mode {
+            Mode::Normal | Mode::Infix if instructions.is_empty() => None,
+            Mode::Normal | Mode::Prefix => {
+                matches_forward(instructions, input, options).map(|end| 0..end)
+            }
+            Mode::Suffix => {
+                matches_backward(instructions, input, options).map(|begin| begin..input.len())
+            }
+            Mode::Infix => {
+                let mut pos = 0;
+                //an empty input is attempted once as well
+                loop {
+                    if let Some(end) = matches_forward(instructions, &input[pos..], options) {
+                        return Some(pos..pos + end);
+                    }
+                    pos = options.unit.forward(input, pos, 1)?;
+                    if pos == input.len() {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Tests whether the edit script applies by applying it, for edit scripts with case
+    /// transformations
+    fn matches_by_applying(&self, input: &str, options: &ApplyOptions) -> Option<Range<usize>> {
+        let instructions: Vec<EditInstruction<&str>> = self
+            .instructions
+            .iter()
+            .map(|instruction| instruction.map(|s| s.as_ref()))
+            .collect();
+        match options.mode.unwrap_or(self.mode) {
+            Mode::Normal if instructions.is_empty() => None,
+            Mode::Normal | Mode::Prefix => apply_forward(&instructions, input, options, &mut None)
+                .ok()
+                .map(|(_, end, _)| 0..end),
+            Mode::Suffix => apply_backward(&instructions, input, options, &mut None)
+                .ok()
+                .map(|(_, begin, input)| begin..input.len()),
+            Mode::Infix if instructions.is_empty() => None,
+            Mode::Infix => {
+                let mut pos = 0;
+                //an empty input is attempted once as well
+                loop {
+                    if let Ok((_, end, _)) =
+                        apply_forward(&instructions, &input[pos..], options, &mut None)
+                    {
+                        return Some(pos..pos + end);
+                    }
+                    pos = options.unit.forward(input, pos, 1)?;
+                    if pos == input.len() {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_case_change<T>(instruction: &EditInstruction<T>) -> bool {
+    matches!(
+        instruction,
+        EditInstruction::LowercaseAll
+            | EditInstruction::UppercaseAll
+            | EditInstruction::LowercaseSpan(_)
+            | EditInstruction::UppercaseSpan(_)
+    )
+}
+
+/// Checks for the instructions that make applying fail regardless of the input
+fn is_applicable<T: AsRef<str>>(
+    instruction: &EditInstruction<T>,
+    instructions: &[EditInstruction<T>],
+) -> bool {
+    match instruction {
+        EditInstruction::InsertionOptions(_) => false,
+        EditInstruction::Captured(_, instruction) => {
+            !matches!(**instruction, EditInstruction::Captured(..))
+        }
+        //a back-reference needs a capture somewhere in the edit script
+        EditInstruction::BackReference(name) => instructions.iter().any(|instruction| {
+            matches!(instruction, EditInstruction::Captured(capture, _) if capture.as_ref() == name.as_ref())
+        }),
+        _ => true,
+    }
+}
+
+/// Matches the instructions from the start of the input, returns the byte offset up to which the
+/// input is consumed
+fn matches_forward<T: AsRef<str>>(
+    instructions: &[EditInstruction<T>],
+    input: &str,
+    options: &ApplyOptions,
+) -> Option<usize> {
+    let unit = options.unit;
+    let mut pos = 0;
+    for (i, instruction) in instructions.iter().enumerate() {
+        let instruction = match instruction {
+            EditInstruction::Captured(_, instruction) => instruction,
+            instruction => instruction,
+        };
+        pos = match instruction {
+            EditInstruction::Identity(s)
+            | EditInstruction::Deletion(s)
+            | EditInstruction::Substitution(s, _) => match_forward(input, pos, s.as_ref(), unit)?,
+            EditInstruction::Transposition(s) => {
+                if unit.count(s.as_ref()) != 2 {
+                    return None;
+                }
+                match_forward(input, pos, s.as_ref(), unit)?
+            }
+            EditInstruction::GenericIdentity(n) => unit.forward(input, pos, *n as usize)?,
+            EditInstruction::WildcardIdentity => {
+                //try the longest match first, until the remaining instructions apply
+                let mut end = input.len();
+                loop {
+                    if let Some(consumed) =
+                        matches_forward(&instructions[i + 1..], &input[end..], options)
+                    {
+                        return Some(end + consumed);
+                    } else if end == pos {
+                        return None;
+                    }
+                    end = unit.backward(input, end, 1).unwrap_or(pos).max(pos);
+                }
+            }
+            EditInstruction::IdentityOptions(v) | EditInstruction::DeletionOptions(v) => {
+                //try the alternatives in order of preference, until the remaining instructions apply
+                let mut previous = None;
+                while let Some((rank, end)) = next_alternative(
+                    v,
+                    previous,
+                    |s| match_forward(input, pos, s, unit).map(|end| (end, end - pos)),
+                    options,
+                ) {
+                    if let Some(consumed) =
+                        matches_forward(&instructions[i + 1..], &input[end..], options)
+                    {
+                        return Some(end + consumed);
+                    }
+                    previous = Some(rank);
+                }
+                return None;
+            }
+            EditInstruction::IdentityClass(name) | EditInstruction::DeletionClass(name) => {
+                let members = options.classes.get(name.as_ref())?;
+                match_class_forward(input, pos, members, unit)?
+            }
+            _ => pos,
+        };
+    }
+    Some(pos)
+}
+
+/// Matches the instructions from the end of the input backwards, returns the byte offset from
+/// which the input is consumed
+fn matches_backward<T: AsRef<str>>(
+    instructions: &[EditInstruction<T>],
+    input: &str,
+    options: &ApplyOptions,
+) -> Option<usize> {
+    let unit = options.unit;
+    let mut end = input.len();
+    for (i, instruction) in instructions.iter().enumerate() {
+        let instruction = match instruction {
+            EditInstruction::Captured(_, instruction) => instruction,
+            instruction => instruction,
+        };
+        end = match instruction {
+            EditInstruction::Identity(s)
+            | EditInstruction::Deletion(s)
+            | EditInstruction::Substitution(s, _) => match_backward(input, end, s.as_ref(), unit)?,
+            EditInstruction::Transposition(s) => {
+                if unit.count(s.as_ref()) != 2 {
+                    return None;
+                }
+                match_backward(input, end, s.as_ref(), unit)?
+            }
+            EditInstruction::GenericIdentity(n) => unit.backward(input, end, *n as usize)?,
+            EditInstruction::WildcardIdentity => {
+                //try the longest match first, until the remaining instructions apply
+                let mut begin = 0;
+                loop {
+                    if let Some(headend) =
+                        matches_backward(&instructions[i + 1..], &input[..begin], options)
+                    {
+                        return Some(headend);
+                    } else if begin == end {
+                        return None;
+                    }
+                    begin = unit.forward(input, begin, 1).unwrap_or(end).min(end);
+                }
+            }
+            EditInstruction::IdentityOptions(v) | EditInstruction::DeletionOptions(v) => {
+                //try the alternatives in order of preference, until the remaining instructions apply
+                let mut previous = None;
+                while let Some((rank, begin)) = next_alternative(
+                    v,
+                    previous,
+                    |s| match_backward(input, end, s, unit).map(|begin| (begin, end - begin)),
+                    options,
+                ) {
+                    if let Some(headend) =
+                        matches_backward(&instructions[i + 1..], &input[..begin], options)
+                    {
+                        return Some(headend);
+                    }
+                    previous = Some(rank);
+                }
+                return None;
+            }
+            EditInstruction::IdentityClass(name) | EditInstruction::DeletionClass(name) => {
+                let members = options.classes.get(name.as_ref())?;
+                match_class_backward(input, end, members, unit)?
+            }
+            _ => end,
+        };
+    }
+    Some(end)
+}
+
+/// Returns the matching alternative that is preferred after the one ranked `previous`, along
+/// with its rank (the key by the option matching policy, and the index in the list).
+/// `matches` returns the offset at the far end of an alternative's match, and its length.
+fn next_alternative<T: AsRef<str>>(
+    alternatives: &[T],
+    previous: Option<(isize, usize)>,
+    matches: impl Fn(&str) -> Option<(usize, usize)>,
+    options: &ApplyOptions,
+) -> Option<((isize, usize), usize)> {
+    alternatives
+        .iter()
+        .enumerate()
+        .filter_map(|(j, s)| {
+            matches(s.as_ref())
+                .map(|(offset, length)| ((options.option_matching.rank(length), j), offset))
+        })
+        .filter(|(rank, _)| previous.is_none_or(|previous| *rank > previous))
+        .min_by_key(|(rank, _)| *rank)
+}
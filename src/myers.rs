@@ -0,0 +1,165 @@
+//! A plain implementation of Myers' O(ND) difference algorithm that works on arbitrary slices.
+//! Unlike `dissimilar`, no semantic cleanup is applied to the result.
+
+use std::ops::Range;
+
+/// An operation in the alignment of a source and a target sequence
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffOp {
+    /// Elements that are equal in both sequences (range in the source, range in the target)
+    Equal(Range<usize>, Range<usize>),
+
+    /// Elements that are only in the source (range in the source)
+    Delete(Range<usize>),
+
+    /// Elements that are only in the target (range in the target)
+    Insert(Range<usize>),
+}
+
+/// Computes a minimal diff between two slices. Adjacent operations of the same kind are merged,
+/// and between two equal stretches all deletions are listed before all insertions.
+pub(crate) fn diff<T: PartialEq>(source: &[T], target: &[T]) -> Vec<DiffOp> {
+    let prefix = source
+        .iter()
+        .zip(target.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = source[prefix..]
+        .iter()
+        .rev()
+        .zip(target[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &source[prefix..source.len() - suffix];
+    let b = &target[prefix..target.len() - suffix];
+
+    let mut ops = Vec::new();
+    if prefix > 0 {
+        ops.push(DiffOp::Equal(0..prefix, 0..prefix));
+    }
+    for (op, x, y) in edits(a, b) {
+        push_op(&mut ops, op, prefix + x, prefix + y);
+    }
+    if suffix > 0 {
+        push_op_range(
+            &mut ops,
+            DiffOp::Equal(
+                source.len() - suffix..source.len(),
+                target.len() - suffix..target.len(),
+            ),
+        );
+    }
+    ops
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Computes the individual edits (in forward order) with the greedy forward algorithm, the
+/// coordinates refer to the source (x) and target (y) element the edit applies to
+fn edits<T: PartialEq>(a: &[T], b: &[T]) -> Vec<(Edit, usize, usize)> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = n + m;
+    if max == 0 {
+        return Vec::new();
+    }
+    let offset = max + 1;
+    let mut v = vec![0isize; (2 * max + 3) as usize];
+    //per step, only the diagonals that the backtracking may consult (-d-1 to d+1) are kept
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    'search: for d in 0..=max {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d
+                || (k != d && v[(k - 1 + offset) as usize] < v[(k + 1 + offset) as usize])
+            {
+                v[(k + 1 + offset) as usize]
+            } else {
+                v[(k - 1 + offset) as usize] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(k + offset) as usize] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    //backtrack through the trace to recover the path
+    let mut result = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let v = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v(k - 1) < v(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            result.push((Edit::Equal, x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                result.push((Edit::Insert, x as usize, prev_y as usize));
+            } else {
+                result.push((Edit::Delete, prev_x as usize, y as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    result.reverse();
+    result
+}
+
+/// Adds a single edit to the list of operations, merging it with what came before
+fn push_op(ops: &mut Vec<DiffOp>, edit: Edit, x: usize, y: usize) {
+    let op = match edit {
+        Edit::Equal => DiffOp::Equal(x..x + 1, y..y + 1),
+        Edit::Delete => DiffOp::Delete(x..x + 1),
+        Edit::Insert => DiffOp::Insert(y..y + 1),
+    };
+    push_op_range(ops, op);
+}
+
+fn push_op_range(ops: &mut Vec<DiffOp>, op: DiffOp) {
+    let len = ops.len();
+    match (op, ops.last_mut()) {
+        (DiffOp::Equal(s, t), Some(DiffOp::Equal(prev_s, prev_t))) => {
+            prev_s.end = s.end;
+            prev_t.end = t.end;
+        }
+        (DiffOp::Delete(s), Some(DiffOp::Delete(prev_s))) => {
+            prev_s.end = s.end;
+        }
+        (DiffOp::Insert(t), Some(DiffOp::Insert(prev_t))) => {
+            prev_t.end = t.end;
+        }
+        (DiffOp::Delete(s), Some(DiffOp::Insert(_))) => {
+            //deletions go before insertions within a change
+            if len >= 2 {
+                if let DiffOp::Delete(prev_s) = &mut ops[len - 2] {
+                    prev_s.end = s.end;
+                    return;
+                }
+            }
+            ops.insert(len - 1, DiffOp::Delete(s));
+        }
+        (op, _) => ops.push(op),
+    }
+}
@@ -0,0 +1,370 @@
+//! Parser for the edit script language (e.g. `=[contar]-[on]`), along with the escaping of
+//! reserved characters in payloads.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::classes::class_reference;
+use crate::{CostModel, EditInstruction, EditScript, Mode, Unit};
+
+/// An error in parsing an edit script, or another textual definition such as a cost table
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The edit script does not conform to the syntax. The location is given as a byte offset
+    /// and as a character offset into the edit script, `found` is `None` at the end of the script.
+    Syntax {
+        offset: usize,
+        charoffset: usize,
+        expected: Expected,
+        found: Option<char>,
+    },
+    /// The edit script does not contain any instructions
+    Empty,
+    /// An error in other input, such as a cost table, class definitions or the value of an option
+    WithMessage(String),
+}
+
+/// What the parser expected where it found something else
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    /// An operator that starts an instruction (`=`, `+`, `-`, `~`, `%`, `&`, `_` or `^`)
+    Operator,
+    /// The opening bracket after the operator, or after the name of a capture
+    OpeningBracket,
+    /// The closing bracket at the end of the payload
+    ClosingBracket,
+    /// A non-empty payload
+    Payload,
+    /// The `>` between the two strings of a substitution
+    SubstitutionSeparator,
+    /// A capture name, consisting of letters and digits
+    CaptureName,
+    /// The payload of a case instruction: `*` or a length (`#n`)
+    CaseSpan,
+    /// A length (the digits of `#n`) that fits in 32 bits
+    Length,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expected::Operator => write!(f, "an operator (= + - ~ % & _ ^)"),
+            Expected::OpeningBracket => write!(f, "["),
+            Expected::ClosingBracket => write!(f, "]"),
+            Expected::Payload => write!(f, "a non-empty payload"),
+            Expected::SubstitutionSeparator => write!(f, ">"),
+            Expected::CaptureName => write!(f, "a capture name of letters and digits"),
+            Expected::CaseSpan => write!(f, "* or a length (#n)"),
+            Expected::Length => write!(f, "a length of at most {}", u32::MAX),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Syntax {
+                charoffset,
+                expected,
+                found: Some(c),
+                ..
+            } => write!(
+                f,
+                "Expected {} at character {} of edit script, found '{}'",
+                expected,
+                charoffset + 1,
+                c
+            ),
+            ParseError::Syntax {
+                charoffset,
+                expected,
+                found: None,
+                ..
+            } => write!(
+                f,
+                "Expected {} at character {} of edit script, found the end",
+                expected,
+                charoffset + 1
+            ),
+            ParseError::Empty => write!(f, "Not a valid edit script, no instructions found"),
+            ParseError::WithMessage(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl FromStr for EditScript<String> {
+    type Err = ParseError;
+
+    /// Parses an edit script. Parsing the notation of an edit script (as written by `Display`)
+    /// yields the same instructions. The mode is not part of the notation, the parsed edit script
+    /// is in normal mode, and its distance is computed with the default costs on characters, as
+    /// for computed edit scripts. A computed edit script in normal mode with the default costs
+    /// therefore round-trips as a whole.
+    fn from_str(editscript: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(editscript);
+        let mut instructions: Vec<EditInstruction<String>> = Vec::new();
+        while !parser.at_end() {
+            instructions.push(parser.instruction()?);
+        }
+        if instructions.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        Ok(EditScript {
+            distance: CostModel::default().distance(&instructions, Unit::Char),
+            instructions,
+            mode: Mode::Normal,
+        })
+    }
+}
+
+impl FromStr for EditInstruction<String> {
+    type Err = ParseError;
+
+    /// Parses a single instruction, nothing may follow it
+    fn from_str(editinstruction: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(editinstruction);
+        let instruction = parser.instruction()?;
+        if !parser.at_end() {
+            return Err(parser.error(parser.pos, Expected::Operator));
+        }
+        Ok(instruction)
+    }
+}
+
+/// Reads instructions from an edit script, keeps track of the byte offset
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.input.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    /// Returns an error at the given byte offset
+    fn error(&self, offset: usize, expected: Expected) -> ParseError {
+        ParseError::Syntax {
+            offset,
+            charoffset: self.input[..offset].chars().count(),
+            expected,
+            found: self.input[offset..].chars().next(),
+        }
+    }
+
+    /// Reads an instruction: an operator, an optional capture name and a payload between brackets
+    fn instruction(&mut self) -> Result<EditInstruction<String>, ParseError> {
+        let start = self.pos;
+        let operator = match self.next() {
+            Some(c) if "=+-~%&_^".contains(c) => c,
+            _ => return Err(self.error(start, Expected::Operator)),
+        };
+        //a named identity that captures what it matches: =name[...]
+        let namestart = self.pos;
+        if operator == '=' {
+            while self.peek().is_some_and(|c| c.is_alphanumeric()) {
+                self.next();
+            }
+        }
+        let name = &self.input[namestart..self.pos];
+        if self.peek() != Some('[') {
+            return Err(self.error(self.pos, Expected::OpeningBracket));
+        }
+        self.next();
+        let payloadstart = self.pos;
+        let mut escaped = false;
+        loop {
+            match self.peek() {
+                None => return Err(self.error(self.pos, Expected::ClosingBracket)),
+                Some(_) if escaped => escaped = false,
+                Some('\\') => escaped = true,
+                Some(']') => break,
+                Some(_) => {}
+            }
+            self.next();
+        }
+        let payloadend = self.pos;
+        self.next();
+        let instruction = self.payload(operator, payloadstart, payloadend)?;
+        if name.is_empty() {
+            Ok(instruction)
+        } else {
+            Ok(EditInstruction::Captured(
+                name.to_string(),
+                Box::new(instruction),
+            ))
+        }
+    }
+
+    /// Interprets the payload (still escaped) between the given byte offsets
+    fn payload(
+        &self,
+        operator: char,
+        start: usize,
+        end: usize,
+    ) -> Result<EditInstruction<String>, ParseError> {
+        let s = &self.input[start..end];
+        if s.is_empty() {
+            return Err(self.error(start, Expected::Payload));
+        }
+        //a leading separator marks options that would otherwise not read as such: a single
+        //alternative, or an empty first one
+        let marked = s.starts_with('|');
+        let alternatives = split_unescaped(if marked { &s[1..] } else { s }, '|');
+        let has_options = marked || alternatives.len() > 1;
+        let options = || alternatives.iter().map(|s| unescape(s)).collect();
+        let instruction = match operator {
+            '+' if has_options => EditInstruction::InsertionOptions(options()),
+            '+' => EditInstruction::Insertion(unescape(s)),
+            '-' => {
+                if let Some(name) = class_reference(s) {
+                    EditInstruction::DeletionClass(name.to_string())
+                } else if has_options {
+                    EditInstruction::DeletionOptions(options())
+                } else {
+                    EditInstruction::Deletion(unescape(s))
+                }
+            }
+            '=' => {
+                if let Some(name) = class_reference(s) {
+                    EditInstruction::IdentityClass(name.to_string())
+                } else if has_options {
+                    EditInstruction::IdentityOptions(options())
+                } else if let Some(n) = self.length(s, start)? {
+                    EditInstruction::GenericIdentity(n)
+                } else if s == "*" {
+                    EditInstruction::WildcardIdentity
+                } else {
+                    EditInstruction::Identity(unescape(s))
+                }
+            }
+            '~' => match split_unescaped(s, '>').split_first() {
+                Some((&"", _)) => return Err(self.error(start, Expected::Payload)),
+                //the target may contain further (unescaped) separators
+                Some((from, to)) if !to.is_empty() => {
+                    let to = &s[from.len() + 1..];
+                    if to.is_empty() {
+                        return Err(self.error(end, Expected::Payload));
+                    }
+                    EditInstruction::Substitution(unescape(from), unescape(to))
+                }
+                _ => return Err(self.error(end, Expected::SubstitutionSeparator)),
+            },
+            '%' => EditInstruction::Transposition(unescape(s)),
+            '&' => match s.char_indices().find(|(_, c)| !c.is_alphanumeric()) {
+                Some((i, _)) => return Err(self.error(start + i, Expected::CaptureName)),
+                None => EditInstruction::BackReference(s.to_string()),
+            },
+            _ => {
+                let uppercase = operator == '^';
+                if s == "*" {
+                    if uppercase {
+                        EditInstruction::UppercaseAll
+                    } else {
+                        EditInstruction::LowercaseAll
+                    }
+                } else if let Some(n) = self.length(s, start)? {
+                    if uppercase {
+                        EditInstruction::UppercaseSpan(n)
+                    } else {
+                        EditInstruction::LowercaseSpan(n)
+                    }
+                } else {
+                    return Err(self.error(start, Expected::CaseSpan));
+                }
+            }
+        };
+        Ok(instruction)
+    }
+
+    /// Reads a payload that denotes a length (`#n`, starting at the given byte offset), `None` if
+    /// it does not. A length that is out of range is an error rather than literal text.
+    fn length(&self, s: &str, start: usize) -> Result<Option<u32>, ParseError> {
+        match s.strip_prefix('#') {
+            Some(n) if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => n
+                .parse()
+                .map(Some)
+                .map_err(|_| self.error(start + 1, Expected::Length)),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Characters that are escaped with a backslash wherever they occur in a payload
+const RESERVED: [char; 4] = ['\\', ']', '|', '>'];
+
+/// Escapes the reserved characters in a payload, as well as a leading character that would
+/// otherwise give the payload a special meaning (e.g. `#` in `=[#3]`)
+pub(crate) fn escape(s: &str, leading: &[char]) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for (i, c) in s.chars().enumerate() {
+        if RESERVED.contains(&c) || (i == 0 && leading.contains(&c)) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Writes the alternatives of options as a payload, separated by `|`. Options with a single
+/// alternative or an empty first alternative get a leading `|`, so they read as options again.
+pub(crate) fn options_payload<T: fmt::Display>(alternatives: &[T]) -> String {
+    let payload = alternatives
+        .iter()
+        .map(|x| escape(&x.to_string(), &[]))
+        .collect::<Vec<String>>()
+        .join("|");
+    if alternatives.len() == 1 || payload.starts_with('|') {
+        "|".to_string() + payload.as_str()
+    } else {
+        payload
+    }
+}
+
+/// Splits a payload at the occurrences of the separator that are not escaped
+fn split_unescaped(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut begin = 0;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == separator {
+            parts.push(&s[begin..i]);
+            begin = i + c.len_utf8();
+        }
+    }
+    parts.push(&s[begin..]);
+    parts
+}
+
+/// Removes the escapes from a payload
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next().or(Some(c))),
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
@@ -0,0 +1,320 @@
+//! Tie-breaking between alignments of equal cost. A run of inserted or deleted characters can
+//! often be placed at several positions with the same result, e.g. when deleting one of two
+//! identical letters; the policy determines which position is chosen.
+
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::backend::to_byte_ranges;
+use crate::myers::DiffOp;
+use crate::ParseError;
+
+/// Policy for choosing between alignments of equal cost
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TieBreaking {
+    /// Place insertions and deletions as close to the start of the string as possible
+    Start,
+
+    /// Place insertions and deletions as close to the end of the string as possible
+    End,
+
+    /// Place insertions and deletions adjacent to other changes where possible, so they are
+    /// grouped into a single change. Otherwise they are placed as close to the end as possible.
+    Grouped,
+}
+
+impl FromStr for TieBreaking {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "start" => Ok(TieBreaking::Start),
+            "end" => Ok(TieBreaking::End),
+            "grouped" => Ok(TieBreaking::Grouped),
+            _ => Err(ParseError::WithMessage(format!(
+                "Unknown tie-breaking policy: {} (expected start, end or grouped)",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Kind {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Moves the pure insertions and deletions in the operations (in forward order) according to the
+/// policy. If the strings are reversed (suffix mode), start and end are interpreted with respect
+/// to the original strings.
+pub(crate) fn break_ties(
+    source: &str,
+    target: &str,
+    ops: Vec<DiffOp>,
+    policy: TieBreaking,
+    reversed: bool,
+) -> Vec<DiffOp> {
+    let sourcechars: Vec<char> = source.chars().collect();
+    let targetchars: Vec<char> = target.chars().collect();
+    //the kind of every aligned character, along with whether it starts an operation (so
+    //consecutive operations of the same kind are not merged)
+    let mut kinds: Vec<Kind> = Vec::new();
+    let mut starts: Vec<bool> = Vec::new();
+    for op in ops {
+        let (kind, length) = match op {
+            DiffOp::Equal(s, _) => (Kind::Equal, source[s].chars().count()),
+            DiffOp::Delete(s) => (Kind::Delete, source[s].chars().count()),
+            DiffOp::Insert(t) => (Kind::Insert, target[t].chars().count()),
+        };
+        kinds.extend(std::iter::repeat_n(kind, length));
+        starts.extend((0..length).map(|i| i == 0));
+    }
+
+    let towards_end = match policy {
+        TieBreaking::Start => reversed,
+        TieBreaking::End | TieBreaking::Grouped => !reversed,
+    };
+    if policy != TieBreaking::Grouped {
+        while shift(
+            &mut kinds,
+            &mut starts,
+            &sourcechars,
+            &targetchars,
+            towards_end,
+        ) {}
+        return to_byte_ranges(source, target, to_ops(&kinds, &starts));
+    }
+    //process the runs in the direction they are moved in, so a moved run never passes an
+    //unprocessed one
+    let mut begin = if towards_end { kinds.len() } else { 0 };
+    while let Some((runbegin, runend)) = next_run(&kinds, begin, towards_end) {
+        begin = if towards_end { runbegin } else { runend };
+        let kind = kinds[runbegin];
+        if kinds[runbegin..runend].iter().any(|k| *k != kind) {
+            //a mix of insertions and deletions is left as is
+            continue;
+        }
+        let chars = if kind == Kind::Delete {
+            &sourcechars
+        } else {
+            &targetchars
+        };
+        let forward = if policy == TieBreaking::Grouped {
+            //does the run become adjacent to another change when moved in this direction?
+            let joins = |forward: bool| {
+                let (b, e) = slide(
+                    &mut kinds.clone(),
+                    &mut starts.clone(),
+                    chars,
+                    runbegin,
+                    runend,
+                    forward,
+                );
+                if forward {
+                    e < kinds.len() && kinds[e] != Kind::Equal
+                } else {
+                    b > 0 && kinds[b - 1] != Kind::Equal
+                }
+            };
+            if joins(towards_end) {
+                towards_end
+            } else if joins(!towards_end) {
+                !towards_end
+            } else {
+                towards_end
+            }
+        } else {
+            towards_end
+        };
+        slide(&mut kinds, &mut starts, chars, runbegin, runend, forward);
+    }
+    to_byte_ranges(source, target, to_ops(&kinds, &starts))
+}
+
+/// Moves (part of) a run of deletions or insertions one position towards the end or the start by
+/// moving an adjacent equal character across it, returns false if nothing can be moved anymore
+fn shift(
+    kinds: &mut [Kind],
+    starts: &mut [bool],
+    sourcechars: &[char],
+    targetchars: &[char],
+    towards_end: bool,
+) -> bool {
+    //indices of the character at each position in the source and target
+    let mut sourceindex = Vec::with_capacity(kinds.len());
+    let mut targetindex = Vec::with_capacity(kinds.len());
+    let (mut i, mut j) = (0, 0);
+    for kind in kinds.iter() {
+        sourceindex.push(i);
+        targetindex.push(j);
+        match kind {
+            Kind::Equal => {
+                i += 1;
+                j += 1;
+            }
+            Kind::Delete => i += 1,
+            Kind::Insert => j += 1,
+        }
+    }
+    let char_at = |position: usize, kind: Kind| match kind {
+        Kind::Insert => targetchars[targetindex[position]],
+        _ => sourcechars[sourceindex[position]],
+    };
+    for position in 0..kinds.len() {
+        if kinds[position] != Kind::Equal {
+            continue;
+        }
+        //the neighbouring run that the equal character may be moved across
+        let neighbour = if towards_end {
+            position.checked_sub(1)
+        } else {
+            Some(position + 1).filter(|next| *next < kinds.len())
+        };
+        let kind = match neighbour.map(|n| kinds[n]) {
+            Some(Kind::Equal) | None => continue,
+            Some(kind) => kind,
+        };
+        //only pure insertions or deletions are moved, a mix of both is left as is
+        let mut block = if towards_end {
+            kinds[..position].iter().rev().collect::<Vec<_>>()
+        } else {
+            kinds[position + 1..].iter().collect::<Vec<_>>()
+        }
+        .into_iter()
+        .take_while(|k| **k != Kind::Equal);
+        if block.any(|k| *k != kind) {
+            continue;
+        }
+        let c = char_at(position, kind);
+        let mut length = 1;
+        loop {
+            //the character of the run that ends up at the other side of the equal character
+            let farthest = if towards_end {
+                match position.checked_sub(length) {
+                    Some(p) if kinds[p] == kind => p,
+                    _ => break,
+                }
+            } else {
+                match position + length {
+                    p if p < kinds.len() && kinds[p] == kind => p,
+                    _ => break,
+                }
+            };
+            if char_at(farthest, kind) == c {
+                if towards_end {
+                    rotate(kinds, starts, farthest..position + 1, true);
+                } else {
+                    rotate(kinds, starts, position..farthest + 1, false);
+                }
+                return true;
+            }
+            length += 1;
+        }
+    }
+    false
+}
+
+/// Moves the last element of the range to the front (right) or the first element to the back
+fn rotate(kinds: &mut [Kind], starts: &mut [bool], range: Range<usize>, right: bool) {
+    if right {
+        kinds[range.clone()].rotate_right(1);
+        starts[range].rotate_right(1);
+    } else {
+        kinds[range.clone()].rotate_left(1);
+        starts[range].rotate_left(1);
+    }
+}
+
+/// Finds the next run of changes, searching forward from `begin` or backward from `begin`
+fn next_run(kinds: &[Kind], begin: usize, backward: bool) -> Option<(usize, usize)> {
+    if backward {
+        let end = kinds[..begin].iter().rposition(|k| *k != Kind::Equal)? + 1;
+        let runbegin = kinds[..end]
+            .iter()
+            .rposition(|k| *k == Kind::Equal)
+            .map_or(0, |i| i + 1);
+        Some((runbegin, end))
+    } else {
+        let runbegin = begin + kinds[begin..].iter().position(|k| *k != Kind::Equal)?;
+        let end = kinds[runbegin..]
+            .iter()
+            .position(|k| *k == Kind::Equal)
+            .map_or(kinds.len(), |i| runbegin + i);
+        Some((runbegin, end))
+    }
+}
+
+/// Slides a run of identical kinds (deletions or insertions) as far as possible in the given
+/// direction by moving equal characters across it, returns the new position of the run
+fn slide(
+    kinds: &mut [Kind],
+    starts: &mut [bool],
+    chars: &[char],
+    mut begin: usize,
+    mut end: usize,
+    forward: bool,
+) -> (usize, usize) {
+    let kind = kinds[begin];
+    let length = end - begin;
+    //index of the first character of the run in the string it belongs to
+    let mut index = kinds[..begin]
+        .iter()
+        .filter(|k| **k == Kind::Equal || **k == kind)
+        .count();
+    if forward {
+        while end < kinds.len()
+            && kinds[end] == Kind::Equal
+            && chars[index] == chars[index + length]
+        {
+            rotate(kinds, starts, begin..end + 1, true);
+            begin += 1;
+            end += 1;
+            index += 1;
+        }
+    } else {
+        while begin > 0
+            && kinds[begin - 1] == Kind::Equal
+            && chars[index - 1] == chars[index - 1 + length]
+        {
+            rotate(kinds, starts, begin - 1..end, false);
+            begin -= 1;
+            end -= 1;
+            index -= 1;
+        }
+    }
+    (begin, end)
+}
+
+/// Converts the kinds back to operations with character indices
+fn to_ops(kinds: &[Kind], starts: &[bool]) -> Vec<DiffOp> {
+    let mut ops = Vec::new();
+    let (mut sourcepos, mut targetpos) = (0, 0);
+    let mut i = 0;
+    while i < kinds.len() {
+        let n = (i + 1..kinds.len())
+            .position(|j| kinds[j] != kinds[i] || (starts[j] && kinds[j] != Kind::Equal))
+            .map_or(kinds.len() - i, |n| n + 1);
+        match kinds[i] {
+            Kind::Equal => {
+                ops.push(DiffOp::Equal(
+                    sourcepos..sourcepos + n,
+                    targetpos..targetpos + n,
+                ));
+                sourcepos += n;
+                targetpos += n;
+            }
+            Kind::Delete => {
+                ops.push(DiffOp::Delete(sourcepos..sourcepos + n));
+                sourcepos += n;
+            }
+            Kind::Insert => {
+                ops.push(DiffOp::Insert(targetpos..targetpos + n));
+                targetpos += n;
+            }
+        }
+        i += n;
+    }
+    ops
+}
@@ -0,0 +1,526 @@
+//! Token-level edit scripts, operating on sequences of tokens (e.g. words) rather than on the
+//! characters of a single string.
+
+use std::fmt;
+use std::ops::Deref;
+
+use crate::myers::{self, DiffOp};
+use crate::{ApplyError, EditInstruction, EditScript, Mode, OptionMatching};
+
+/// A borrowed sequence of tokens, this is the payload of token-level edit instructions.
+/// When displayed, the tokens are joined with a single space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenSeq<'a, T>(pub &'a [T]);
+
+impl<T> Deref for TokenSeq<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.0
+    }
+}
+
+impl<T> AsRef<[T]> for TokenSeq<'_, T> {
+    fn as_ref(&self) -> &[T] {
+        self.0
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for TokenSeq<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, token) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", token)?;
+        }
+        fmt::Result::Ok(())
+    }
+}
+
+/// Compute the shortest edit script (Myers' diff) between a source and target sequence of tokens
+/// Returns an edit script with borrowed references to the original sequences
+pub fn shortest_edit_script_tokens<'a, T: PartialEq>(
+    source: &'a [T],
+    target: &'a [T],
+    prefix: bool,
+    generic: bool,
+    allow_substitutions: bool,
+) -> EditScript<TokenSeq<'a, T>> {
+    let ops = myers::diff(source, target);
+    let mut editscript = build_editscript(source, target, ops, generic, allow_substitutions);
+    if prefix {
+        strip_trailing_identities(&mut editscript.instructions);
+        editscript.mode = Mode::Prefix;
+    }
+    editscript
+}
+
+/// Compute the shortest edit script (Myers' diff) between a source and target sequence of tokens
+/// where we look at suffixes and strip common prefixes. The instructions run from the last token
+/// to the first, like with [`crate::shortest_edit_script_suffix`].
+pub fn shortest_edit_script_tokens_suffix<'a, T: PartialEq>(
+    source: &'a [T],
+    target: &'a [T],
+    generic: bool,
+    allow_substitutions: bool,
+) -> EditScript<TokenSeq<'a, T>> {
+    let reversed_source: Vec<&T> = source.iter().rev().collect();
+    let reversed_target: Vec<&T> = target.iter().rev().collect();
+    //map the ranges on the reversed sequences back to the original sequences
+    let ops = myers::diff(&reversed_source, &reversed_target)
+        .into_iter()
+        .map(|op| match op {
+            DiffOp::Equal(s, t) => DiffOp::Equal(
+                source.len() - s.end..source.len() - s.start,
+                target.len() - t.end..target.len() - t.start,
+            ),
+            DiffOp::Delete(s) => DiffOp::Delete(source.len() - s.end..source.len() - s.start),
+            DiffOp::Insert(t) => DiffOp::Insert(target.len() - t.end..target.len() - t.start),
+        })
+        .collect();
+    let mut editscript = build_editscript(source, target, ops, generic, allow_substitutions);
+    strip_trailing_identities(&mut editscript.instructions);
+    editscript.mode = Mode::Suffix;
+    editscript
+}
+
+fn build_editscript<'a, T>(
+    source: &'a [T],
+    target: &'a [T],
+    ops: Vec<DiffOp>,
+    generic: bool,
+    allow_substitutions: bool,
+) -> EditScript<TokenSeq<'a, T>> {
+    let mut prev: isize = 0;
+    let mut distance = 0;
+    let mut instructions = Vec::with_capacity(ops.len());
+    for op in ops {
+        match op {
+            DiffOp::Equal(s, _) => {
+                if generic {
+                    instructions.push(EditInstruction::GenericIdentity(s.len() as u32));
+                } else {
+                    instructions.push(EditInstruction::Identity(TokenSeq(&source[s])));
+                }
+                prev = 0;
+            }
+            DiffOp::Delete(s) => {
+                let length = s.len() as isize;
+                let is_substitution = prev > 0 && length == prev;
+                if is_substitution && allow_substitutions {
+                    if let Some(EditInstruction::Insertion(t)) = instructions.pop() {
+                        instructions.push(EditInstruction::Substitution(TokenSeq(&source[s]), t));
+                    }
+                    prev = 0;
+                } else {
+                    distance += length;
+                    instructions.push(EditInstruction::Deletion(TokenSeq(&source[s])));
+                    prev = -length;
+                }
+            }
+            DiffOp::Insert(t) => {
+                let length = t.len() as isize;
+                let is_substitution = prev < 0 && -length == prev;
+                if is_substitution && allow_substitutions {
+                    if let Some(EditInstruction::Deletion(s)) = instructions.pop() {
+                        instructions.push(EditInstruction::Substitution(s, TokenSeq(&target[t])));
+                    }
+                    prev = 0;
+                } else {
+                    distance += length;
+                    instructions.push(EditInstruction::Insertion(TokenSeq(&target[t])));
+                    prev = length;
+                }
+            }
+        }
+    }
+    EditScript {
+        instructions,
+        mode: Mode::Normal,
+        distance: distance as u32,
+    }
+}
+
+fn strip_trailing_identities<T>(instructions: &mut Vec<EditInstruction<T>>) {
+    while let Some(EditInstruction::Identity(_)) | Some(EditInstruction::GenericIdentity(_)) =
+        instructions.last()
+    {
+        instructions.pop();
+    }
+}
+
+pub trait ApplyTokenEditScript<T> {
+    /// Applies a token-level edit script to a sequence of tokens
+    fn apply_to(&self, input: &[T], mode: Option<Mode>) -> Result<Vec<T>, ApplyError> {
+        self.apply_matching(input, mode, OptionMatching::default())
+    }
+
+    /// Applies a token-level edit script to a sequence of tokens, choosing between the
+    /// alternatives of options according to the given policy
+    fn apply_matching(
+        &self,
+        input: &[T],
+        mode: Option<Mode>,
+        option_matching: OptionMatching,
+    ) -> Result<Vec<T>, ApplyError>;
+}
+
+impl<T: PartialEq + Clone + fmt::Display> ApplyTokenEditScript<T> for EditScript<TokenSeq<'_, T>> {
+    fn apply_matching(
+        &self,
+        input: &[T],
+        mode: Option<Mode>,
+        option_matching: OptionMatching,
+    ) -> Result<Vec<T>, ApplyError> {
+        let mode = mode.unwrap_or(self.mode);
+        match mode {
+            Mode::Infix => {
+                let mut result: Vec<T> = Vec::with_capacity(input.len());
+                let mut matches = false;
+                //the error at the first position is reported if the script matches nowhere
+                let mut error = None;
+                let mut i = 0;
+                if self.instructions.is_empty() {
+                    return Err(ApplyError::EmptyScript);
+                }
+                //an empty input is attempted once as well
+                while i < input.len() || (input.is_empty() && error.is_none() && !matches) {
+                    match apply_forward(&self.instructions, &input[i..], option_matching) {
+                        Ok((output, consumed)) if consumed > 0 => {
+                            matches = true;
+                            result.extend(output);
+                            i += consumed;
+                            continue;
+                        }
+                        Ok((output, _)) => {
+                            matches = true;
+                            result.extend(output);
+                        }
+                        Err(err) if err.is_permanent() => return Err(err),
+                        Err(err) => {
+                            error.get_or_insert(err.shifted(0, i));
+                        }
+                    }
+                    result.extend(input.get(i).cloned());
+                    i += 1;
+                }
+                match error {
+                    Some(err) if !matches => Err(err),
+                    _ => Ok(result),
+                }
+            }
+            Mode::Suffix => apply_backward(&self.instructions, input, option_matching),
+            Mode::Prefix => {
+                let (mut output, consumed) =
+                    apply_forward(&self.instructions, input, option_matching)?;
+                output.extend_from_slice(&input[consumed..]);
+                Ok(output)
+            }
+            Mode::Normal => {
+                if self.instructions.is_empty() {
+                    return Err(ApplyError::EmptyScript);
+                }
+                apply_forward(&self.instructions, input, option_matching).map(|(output, _)| output)
+            }
+        }
+    }
+}
+
+/// Applies the instructions from the start of the input, returns the output and the number of
+/// input tokens that were consumed
+fn apply_forward<T: PartialEq + Clone + fmt::Display>(
+    instructions: &[EditInstruction<TokenSeq<'_, T>>],
+    input: &[T],
+    option_matching: OptionMatching,
+) -> Result<(Vec<T>, usize), ApplyError> {
+    let mut output: Vec<T> = Vec::with_capacity(input.len());
+    let mut pos = 0;
+    for (i, instruction) in instructions.iter().enumerate() {
+        let remainder = &input[pos..];
+        let mismatch = || mismatch_forward(input, pos, instruction, i);
+        match instruction {
+            EditInstruction::Insertion(s) => output.extend_from_slice(s),
+            EditInstruction::Deletion(s) => {
+                if !remainder.starts_with(s) {
+                    return Err(mismatch());
+                }
+                pos += s.len();
+            }
+            EditInstruction::Identity(s) => {
+                if !remainder.starts_with(s) {
+                    return Err(mismatch());
+                }
+                output.extend_from_slice(s);
+                pos += s.len();
+            }
+            EditInstruction::GenericIdentity(keeplength) => {
+                let keeplength = *keeplength as usize;
+                if keeplength > remainder.len() {
+                    return Err(mismatch());
+                }
+                output.extend_from_slice(&remainder[..keeplength]);
+                pos += keeplength;
+            }
+            EditInstruction::WildcardIdentity => {
+                //try the longest match first, until the remaining instructions apply
+                let mut end = input.len();
+                loop {
+                    match apply_forward(&instructions[i + 1..], &input[end..], option_matching) {
+                        Ok((rest, consumed)) => {
+                            output.extend_from_slice(&input[pos..end]);
+                            output.extend(rest);
+                            return Ok((output, end + consumed));
+                        }
+                        Err(err) if end == pos || err.is_permanent() => {
+                            return Err(err.shifted(i + 1, end))
+                        }
+                        Err(_) => end -= 1,
+                    }
+                }
+            }
+            EditInstruction::IdentityOptions(alternatives)
+            | EditInstruction::DeletionOptions(alternatives) => {
+                let lengths = alternatives
+                    .iter()
+                    .filter(|s| remainder.starts_with(s))
+                    .map(|s| s.len());
+                let rest = &instructions[i + 1..];
+                let length = option_matching
+                    .choose(lengths, |length| {
+                        apply_forward(rest, &remainder[length..], option_matching).is_ok()
+                    })
+                    .ok_or_else(mismatch)?;
+                if matches!(instruction, EditInstruction::IdentityOptions(_)) {
+                    output.extend_from_slice(&remainder[..length]);
+                }
+                pos += length;
+            }
+            EditInstruction::Substitution(s, t) => {
+                if !remainder.starts_with(s) {
+                    return Err(mismatch());
+                }
+                output.extend_from_slice(t);
+                pos += s.len();
+            }
+            EditInstruction::Transposition(s) => {
+                if s.len() != 2 {
+                    return Err(transposition_length(i));
+                }
+                if !remainder.starts_with(s) {
+                    return Err(mismatch());
+                }
+                output.push(s[1].clone());
+                output.push(s[0].clone());
+                pos += 2;
+            }
+            EditInstruction::InsertionOptions(_) => {
+                return Err(ApplyError::Ambiguous { index: i });
+            }
+            EditInstruction::IdentityClass(_) | EditInstruction::DeletionClass(_) => {
+                return Err(ApplyError::Unsupported {
+                    index: i,
+                    reason: "character classes can not be applied to token sequences".to_string(),
+                });
+            }
+            EditInstruction::Captured(..) | EditInstruction::BackReference(_) => {
+                return Err(ApplyError::Unsupported {
+                    index: i,
+                    reason: "captures can not be applied to token sequences".to_string(),
+                });
+            }
+            EditInstruction::LowercaseAll
+            | EditInstruction::UppercaseAll
+            | EditInstruction::LowercaseSpan(_)
+            | EditInstruction::UppercaseSpan(_) => {
+                return Err(ApplyError::Unsupported {
+                    index: i,
+                    reason: "case transformations can not be applied to token sequences"
+                        .to_string(),
+                });
+            }
+        }
+    }
+    Ok((output, pos))
+}
+
+/// Applies the instructions from the end of the input backwards (suffix mode)
+fn apply_backward<T: PartialEq + Clone + fmt::Display>(
+    instructions: &[EditInstruction<TokenSeq<'_, T>>],
+    input: &[T],
+    option_matching: OptionMatching,
+) -> Result<Vec<T>, ApplyError> {
+    let mut tail: Vec<T> = Vec::new();
+    let mut end = input.len();
+    for (i, instruction) in instructions.iter().enumerate() {
+        let head = &input[..end];
+        let mismatch = || mismatch_backward(input, end, instruction, i);
+        match instruction {
+            EditInstruction::Insertion(s) => {
+                tail.splice(0..0, s.iter().cloned());
+            }
+            EditInstruction::Deletion(s) => {
+                if !head.ends_with(s) {
+                    return Err(mismatch());
+                }
+                end -= s.len();
+            }
+            EditInstruction::Identity(s) => {
+                if !head.ends_with(s) {
+                    return Err(mismatch());
+                }
+                end -= s.len();
+                tail.splice(0..0, input[end..end + s.len()].iter().cloned());
+            }
+            EditInstruction::GenericIdentity(keeplength) => {
+                let keeplength = *keeplength as usize;
+                if keeplength > head.len() {
+                    return Err(mismatch());
+                }
+                end -= keeplength;
+                tail.splice(0..0, input[end..end + keeplength].iter().cloned());
+            }
+            EditInstruction::WildcardIdentity => {
+                //try the longest match first, until the remaining instructions apply
+                let mut begin = 0;
+                loop {
+                    match apply_backward(&instructions[i + 1..], &input[..begin], option_matching) {
+                        Ok(mut result) => {
+                            result.extend_from_slice(&input[begin..end]);
+                            result.extend(tail);
+                            return Ok(result);
+                        }
+                        Err(err) if begin == end || err.is_permanent() => {
+                            return Err(err.shifted(i + 1, 0))
+                        }
+                        Err(_) => begin += 1,
+                    }
+                }
+            }
+            EditInstruction::IdentityOptions(alternatives)
+            | EditInstruction::DeletionOptions(alternatives) => {
+                let lengths = alternatives
+                    .iter()
+                    .filter(|s| head.ends_with(s))
+                    .map(|s| s.len());
+                let rest = &instructions[i + 1..];
+                let length = option_matching
+                    .choose(lengths, |length| {
+                        apply_backward(rest, &head[..head.len() - length], option_matching).is_ok()
+                    })
+                    .ok_or_else(mismatch)?;
+                end -= length;
+                if matches!(instruction, EditInstruction::IdentityOptions(_)) {
+                    tail.splice(0..0, input[end..end + length].iter().cloned());
+                }
+            }
+            EditInstruction::Substitution(s, t) => {
+                if !head.ends_with(s) {
+                    return Err(mismatch());
+                }
+                end -= s.len();
+                tail.splice(0..0, t.iter().cloned());
+            }
+            EditInstruction::Transposition(s) => {
+                if s.len() != 2 {
+                    return Err(transposition_length(i));
+                }
+                if !head.ends_with(s) {
+                    return Err(mismatch());
+                }
+                end -= 2;
+                tail.splice(0..0, [s[1].clone(), s[0].clone()]);
+            }
+            EditInstruction::InsertionOptions(_) => {
+                return Err(ApplyError::Ambiguous { index: i });
+            }
+            EditInstruction::IdentityClass(_) | EditInstruction::DeletionClass(_) => {
+                return Err(ApplyError::Unsupported {
+                    index: i,
+                    reason: "character classes can not be applied to token sequences".to_string(),
+                });
+            }
+            EditInstruction::Captured(..) | EditInstruction::BackReference(_) => {
+                return Err(ApplyError::Unsupported {
+                    index: i,
+                    reason: "captures can not be applied to token sequences".to_string(),
+                });
+            }
+            EditInstruction::LowercaseAll
+            | EditInstruction::UppercaseAll
+            | EditInstruction::LowercaseSpan(_)
+            | EditInstruction::UppercaseSpan(_) => {
+                return Err(ApplyError::Unsupported {
+                    index: i,
+                    reason: "case transformations can not be applied to token sequences"
+                        .to_string(),
+                });
+            }
+        }
+    }
+    let mut result = input[..end].to_vec();
+    result.extend(tail);
+    Ok(result)
+}
+
+fn transposition_length(i: usize) -> ApplyError {
+    ApplyError::Unsupported {
+        index: i,
+        reason: "a transposition takes exactly two tokens".to_string(),
+    }
+}
+
+/// Returns what an instruction requires of the input, in edit script notation, along with the
+/// number of tokens it covers
+fn expectation<T: fmt::Display>(instruction: &EditInstruction<TokenSeq<'_, T>>) -> (String, usize) {
+    match instruction {
+        EditInstruction::Identity(s)
+        | EditInstruction::Deletion(s)
+        | EditInstruction::Substitution(s, _)
+        | EditInstruction::Transposition(s) => (s.to_string(), s.len()),
+        EditInstruction::IdentityOptions(v) | EditInstruction::DeletionOptions(v) => (
+            v.iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>()
+                .join("|"),
+            v.iter().map(|s| s.len()).max().unwrap_or(0),
+        ),
+        EditInstruction::GenericIdentity(n) => (format!("#{}", n), *n as usize),
+        _ => (instruction.to_string(), 0),
+    }
+}
+
+/// Returns the error for an instruction (at index `i`) that does not match the input at the given
+/// token offset, when applying forward
+fn mismatch_forward<T: fmt::Display>(
+    input: &[T],
+    pos: usize,
+    instruction: &EditInstruction<TokenSeq<'_, T>>,
+    i: usize,
+) -> ApplyError {
+    let (expected, length) = expectation(instruction);
+    ApplyError::NoMatch {
+        index: i,
+        position: pos,
+        expected,
+        found: TokenSeq(&input[pos..(pos + length).min(input.len())]).to_string(),
+    }
+}
+
+/// Returns the error for an instruction (at index `i`) that does not match the input right before
+/// the given token offset, when applying backward
+fn mismatch_backward<T: fmt::Display>(
+    input: &[T],
+    end: usize,
+    instruction: &EditInstruction<TokenSeq<'_, T>>,
+    i: usize,
+) -> ApplyError {
+    let (expected, length) = expectation(instruction);
+    let begin = end.saturating_sub(length);
+    ApplyError::NoMatch {
+        index: i,
+        position: begin,
+        expected,
+        found: TokenSeq(&input[begin..end]).to_string(),
+    }
+}
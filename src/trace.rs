@@ -0,0 +1,113 @@
+//! Traces of applying edit scripts, reporting what every instruction consumed and produced.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::EditInstruction;
+
+/// What a single instruction did when applying an edit script. Spans are character offsets into
+/// the input (after normalization and case transformations) and into the output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceStep {
+    /// The index of the instruction in the edit script, starting at zero
+    pub index: usize,
+    /// The instruction, in edit script notation
+    pub instruction: String,
+    /// The span of the input that the instruction consumed
+    pub source: Range<usize>,
+    /// The span of the output that the instruction produced
+    pub target: Range<usize>,
+    /// The text that the instruction consumed
+    pub consumed: String,
+    /// The text that the instruction produced
+    pub produced: String,
+    /// The state after the instruction: the output up to here in normal, prefix and infix mode,
+    /// the input that remains to be processed in suffix mode
+    pub head: String,
+    /// The state after the instruction: the input that remains to be processed in normal, prefix
+    /// and infix mode, the output from here on in suffix mode
+    pub tail: String,
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\tconsumed '{}' ({}..{})\tproduced '{}' ({}..{})\thead '{}'\ttail '{}'",
+            self.index + 1,
+            self.instruction,
+            self.consumed,
+            self.source.start,
+            self.source.end,
+            self.produced,
+            self.target.start,
+            self.target.end,
+            self.head,
+            self.tail
+        )
+    }
+}
+
+/// A step as recorded while applying, with byte offsets. The spans are relative to the input and
+/// output of the pass that recorded it. When applying forward, they are measured from the start,
+/// when applying backward from the end, as case transformations may change the length of the
+/// input before it.
+#[derive(Debug, Clone)]
+pub(crate) struct Step {
+    pub index: usize,
+    pub source: Range<usize>,
+    pub target: Range<usize>,
+}
+
+impl Step {
+    /// Adjusts a step that was recorded for the remainder of an edit script (from instruction
+    /// `index`), applied to the remainder of the input (from byte `source`), with output that goes
+    /// after byte `target`
+    pub fn shifted(self, index: usize, source: usize, target: usize) -> Self {
+        Step {
+            index: self.index + index,
+            source: self.source.start + source..self.source.end + source,
+            target: self.target.start + target..self.target.end + target,
+        }
+    }
+}
+
+/// Converts the recorded steps into the trace, given the final input and output. Steps recorded
+/// backward have their spans measured from the end of the input and output.
+pub(crate) fn finish(
+    steps: Vec<Step>,
+    instructions: &[EditInstruction<&str>],
+    input: &str,
+    output: &str,
+    backward: bool,
+) -> Vec<TraceStep> {
+    let chars = |s: &str, offset: usize| s[..offset].chars().count();
+    steps
+        .into_iter()
+        .map(|step| {
+            let (source, target) = if backward {
+                (
+                    input.len() - step.source.end..input.len() - step.source.start,
+                    output.len() - step.target.end..output.len() - step.target.start,
+                )
+            } else {
+                (step.source, step.target)
+            };
+            let (head, tail) = if backward {
+                (&input[..source.start], &output[target.start..])
+            } else {
+                (&output[..target.end], &input[source.end..])
+            };
+            TraceStep {
+                index: step.index,
+                instruction: instructions[step.index].to_string(),
+                source: chars(input, source.start)..chars(input, source.end),
+                target: chars(output, target.start)..chars(output, target.end),
+                consumed: input[source].to_string(),
+                produced: output[target].to_string(),
+                head: head.to_string(),
+                tail: tail.to_string(),
+            }
+        })
+        .collect()
+}
@@ -1,3 +1,6 @@
+//the early tests index instructions with get(0) and compare booleans with assert_eq!
+#![allow(clippy::get_first, clippy::bool_assert_comparison)]
+
 //#[macro_use]
 //extern crate matches;
 
@@ -126,3 +129,1135 @@ fn test0014_apply_deleteoptions() {
     assert_eq!(format!("{}",editscript.apply_to("hihaho", Some(Mode::Infix)).unwrap() ),"hehehe");
 }
 
+
+#[test]
+fn test0015_diff_tokens() {
+    let source = ["the", "old", "man", "walked", "home"];
+    let target = ["the", "old", "men", "walked", "home"];
+    let editscript = shortest_edit_script_tokens(&source, &target, false, false, true);
+    assert_eq!(format!("{}", editscript), "=[the old]~[man>men]=[walked home]");
+    assert_eq!(editscript.distance, 1);
+    let editscript = shortest_edit_script_tokens(&source, &target, true, false, false);
+    assert_eq!(format!("{}", editscript), "=[the old]-[man]+[men]");
+    assert_eq!(editscript.distance, 2);
+}
+
+#[test]
+fn test0016_diff_tokens_suffix() {
+    let source = ["ik", "heb", "gelopen"];
+    let target = ["lopen"];
+    let editscript = shortest_edit_script_tokens_suffix(&source, &target, false, false);
+    assert_eq!(format!("{}", editscript), "-[ik heb gelopen]+[lopen]");
+    let source = ["de", "rode", "huizen"];
+    let target = ["de", "rode", "huis"];
+    let editscript = shortest_edit_script_tokens_suffix(&source, &target, true, false);
+    assert_eq!(format!("{}", editscript), "-[huizen]+[huis]");
+}
+
+#[test]
+fn test0017_apply_tokens() {
+    let source = ["ye", "olde", "shoppe"];
+    let target = ["the", "old", "shop"];
+    let editscript = shortest_edit_script_tokens(&source, &target, false, false, true);
+    assert_eq!(editscript.apply_to(&source, None).unwrap(), target);
+
+    let editscript = shortest_edit_script_tokens_suffix(&source, &target, false, true);
+    assert_eq!(editscript.apply_to(&source, None).unwrap(), target);
+    assert!(editscript.apply_to(&["a", "b"], None).is_err());
+}
+
+#[test]
+fn test0018_apply_tokens_infix() {
+    let source = ["olde"];
+    let target = ["old"];
+    let editscript = shortest_edit_script_tokens(&source, &target, false, false, true);
+    assert_eq!(
+        editscript
+            .apply_to(&["ye", "olde", "shoppe", "olde"], Some(Mode::Infix))
+            .unwrap(),
+        ["ye", "old", "shoppe", "old"]
+    );
+}
+
+#[test]
+fn test0019_diff_graphemes() {
+    //decomposed unicode, the diacritics are combining characters
+    let source = "vie\u{302}t";
+    let target = "vie\u{323}\u{302}t";
+    let options = DiffOptions {
+        unit: Unit::Grapheme,
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with(source, target, &options);
+    assert_eq!(
+        format!("{}", editscript),
+        "=[vi]~[e\u{302}>e\u{323}\u{302}]=[t]"
+    );
+    assert_eq!(editscript.distance, 1);
+
+    let options = DiffOptions {
+        mode: Mode::Suffix,
+        generic: true,
+        unit: Unit::Grapheme,
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with(source, target, &options);
+    assert_eq!(
+        format!("{}", editscript),
+        "=[#1]~[e\u{302}>e\u{323}\u{302}]"
+    );
+}
+
+#[test]
+fn test0020_apply_graphemes() {
+    let options = ApplyOptions {
+        mode: Some(Mode::Suffix),
+        unit: Unit::Grapheme,
+        ..Default::default()
+    };
+    let editscript: EditScript<String> = EditScript::from_str("=[#1]-[e\u{302}]+[e\u{323}\u{302}]").unwrap();
+    assert_eq!(
+        editscript.apply_with("tie\u{302}t", &options).unwrap(),
+        "tie\u{323}\u{302}t"
+    );
+
+    //an identity check must not match half a cluster
+    let editscript: EditScript<String> = EditScript::from_str("=[ca]-[fe]+[ff]").unwrap();
+    assert!(editscript.apply_to("cafe\u{301}", None).is_ok());
+    assert!(editscript
+        .apply_with(
+            "cafe\u{301}",
+            &ApplyOptions {
+                unit: Unit::Grapheme,
+                ..Default::default()
+            }
+        )
+        .is_err());
+}
+
+#[test]
+fn test0021_apply_prefix_keeps_remainder() {
+    let editscript = shortest_edit_script("gelopen", "lopen", true, false, false);
+    assert_eq!(format!("{}", editscript), "-[ge]");
+    assert_eq!(editscript.apply_to("gelopen", None).unwrap(), "lopen");
+}
+
+#[test]
+fn test0022_diff_normalization() {
+    //the same pair in composed and decomposed form
+    let options = DiffOptions {
+        normalization: Some(Normalization::NFC),
+        ..Default::default()
+    };
+    let composed = shortest_edit_script_with("caf\u{e9}s", "caf\u{e9}", &options);
+    let decomposed = shortest_edit_script_with("cafe\u{301}s", "cafe\u{301}", &options);
+    assert_eq!(composed, decomposed);
+    assert_eq!(format!("{}", composed), "=[caf\u{e9}]-[s]");
+}
+
+#[test]
+fn test0023_apply_normalization() {
+    //a script learned on decomposed text, applied to composed text
+    let editscript = shortest_edit_script_with("cafe\u{301}s", "cafe\u{301}", &DiffOptions::default());
+    assert!(editscript.apply_to("caf\u{e9}s", None).is_err());
+    let options = ApplyOptions {
+        normalization: Some(Normalization::NFC),
+        ..Default::default()
+    };
+    assert_eq!(editscript.apply_with("caf\u{e9}s", &options).unwrap(), "caf\u{e9}");
+    assert_eq!(editscript.apply_with("cafe\u{301}s", &options).unwrap(), "caf\u{e9}");
+}
+
+#[test]
+fn test0024_diff_case() {
+    let options = DiffOptions {
+        factor_case: true,
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with("Häuser", "haus", &options);
+    assert_eq!(format!("{}", editscript), "_[*]=[h]~[ä>a]=[us]-[er]");
+    assert_eq!(editscript.distance, 4);
+    let editscript = shortest_edit_script_with("paris", "Paris", &options);
+    assert_eq!(format!("{}", editscript), "^[#1]=[Paris]");
+    assert_eq!(editscript.distance, 1);
+
+    let options = DiffOptions {
+        mode: Mode::Suffix,
+        factor_case: true,
+        ..Default::default()
+    };
+    //the same rule regardless of the case of the input
+    assert_eq!(
+        shortest_edit_script_with("Häuser", "haus", &options),
+        shortest_edit_script_with("HÄUSER", "haus", &options)
+    );
+}
+
+#[test]
+fn test0025_parse_case() {
+    let editscript = EditScript::from_str("_[*]^[#2]=[ab]^[*]_[#1]").unwrap();
+    assert_eq!(editscript.instructions[0], EditInstruction::LowercaseAll);
+    assert_eq!(editscript.instructions[1], EditInstruction::UppercaseSpan(2));
+    assert_eq!(editscript.instructions[3], EditInstruction::UppercaseAll);
+    assert_eq!(editscript.instructions[4], EditInstruction::LowercaseSpan(1));
+    assert_eq!(format!("{}", editscript), "_[*]^[#2]=[ab]^[*]_[#1]");
+    assert!(EditScript::from_str("^[x]").is_err());
+}
+
+#[test]
+fn test0026_apply_case() {
+    let editscript: EditScript<String> = EditScript::from_str("^[#1]=[Paris]").unwrap();
+    assert_eq!(editscript.apply_to("paris", None).unwrap(), "Paris");
+    let editscript: EditScript<String> = EditScript::from_str("_[*]-[er]=[#2]-[ä]+[a]").unwrap();
+    assert_eq!(editscript.apply_to("HÄUSER", Some(Mode::Suffix)).unwrap(), "haus");
+    assert_eq!(editscript.apply_to("Mäuser", Some(Mode::Suffix)).unwrap(), "maus");
+    let editscript: EditScript<String> = EditScript::from_str("^[#1]").unwrap();
+    assert_eq!(editscript.apply_to("new york", Some(Mode::Prefix)).unwrap(), "New york");
+}
+
+#[test]
+fn test0027_diff_substitution() {
+    let editscript = shortest_edit_script("pidieron", "pedir", false, false, true);
+    assert_eq!(format!("{}", editscript), "=[p]~[i>e]=[di]-[eron]+[r]");
+    assert_eq!(
+        editscript.instructions[1],
+        EditInstruction::Substitution("i", "e")
+    );
+    assert_eq!(editscript.distance, 6);
+    let editscript = shortest_edit_script_suffix("pidieron", "pedir", false, true);
+    assert_eq!(format!("{}", editscript), "-[eron]+[r]=[di]~[i>e]");
+}
+
+#[test]
+fn test0028_parse_substitution() {
+    let editscript = EditScript::from_str("=[p]~[i>e]=[di]").unwrap();
+    assert_eq!(
+        editscript.instructions[1],
+        EditInstruction::Substitution("i".to_string(), "e".to_string())
+    );
+    assert_eq!(format!("{}", editscript), "=[p]~[i>e]=[di]");
+    assert!(EditScript::from_str("~[ie]").is_err());
+}
+
+#[test]
+fn test0029_apply_substitution() {
+    let editscript: EditScript<String> = EditScript::from_str("=[p]~[i>e]=[di]-[eron]+[r]").unwrap();
+    assert_eq!(editscript.apply_to("pidieron", None).unwrap(), "pedir");
+    let editscript: EditScript<String> = EditScript::from_str("-[eron]+[r]=[di]~[i>e]").unwrap();
+    assert_eq!(editscript.apply_to("pidieron", Some(Mode::Suffix)).unwrap(), "pedir");
+    assert!(editscript.apply_to("podieron", Some(Mode::Suffix)).is_err());
+}
+
+#[test]
+fn test0030_diff_transposition() {
+    let options = DiffOptions {
+        allow_transpositions: true,
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with("teh", "the", &options);
+    assert_eq!(format!("{}", editscript), "=[t]%[eh]");
+    assert_eq!(
+        editscript.instructions[1],
+        EditInstruction::Transposition("eh".to_string())
+    );
+    assert_eq!(editscript.distance, 1);
+    let editscript = shortest_edit_script_with("recieve", "receive", &options);
+    assert_eq!(format!("{}", editscript), "=[rec]%[ie]=[ve]");
+    assert_eq!(editscript.distance, 1);
+    let options = DiffOptions {
+        mode: Mode::Suffix,
+        ..options
+    };
+    let editscript = shortest_edit_script_with("recieve", "receive", &options);
+    assert_eq!(format!("{}", editscript), "=[ve]%[ie]");
+    //not enabled by default
+    let editscript = shortest_edit_script_with("teh", "the", &DiffOptions::default());
+    assert_eq!(editscript.distance, 2);
+}
+
+#[test]
+fn test0031_parse_transposition() {
+    let editscript = EditScript::from_str("=[t]%[eh]").unwrap();
+    assert_eq!(
+        editscript.instructions[1],
+        EditInstruction::Transposition("eh".to_string())
+    );
+    assert_eq!(editscript.distance, 1);
+    assert_eq!(format!("{}", editscript), "=[t]%[eh]");
+}
+
+#[test]
+fn test0032_apply_transposition() {
+    let editscript: EditScript<String> = EditScript::from_str("=[t]%[eh]").unwrap();
+    assert_eq!(editscript.apply_to("teh", None).unwrap(), "the");
+    assert!(editscript.apply_to("the", None).is_err());
+    let editscript: EditScript<String> = EditScript::from_str("=[ve]%[ie]").unwrap();
+    assert_eq!(editscript.apply_to("recieve", Some(Mode::Suffix)).unwrap(), "receive");
+    let editscript: EditScript<String> = EditScript::from_str("%[abc]").unwrap();
+    assert!(editscript.apply_to("abc", None).is_err());
+}
+
+#[test]
+fn test0033_costmodel() {
+    let mut costs = CostModel::default();
+    costs.insertion = 2;
+    costs.substitution = 3;
+    costs.set_pair_cost("é", "e", 0);
+    assert_eq!(costs.pair_cost("é", "e"), Some(0));
+    assert_eq!(costs.pair_cost("e", "é"), None);
+    let options = DiffOptions {
+        costs,
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with("café", "cafe", &options);
+    assert_eq!(format!("{}", editscript), "=[caf]~[é>e]");
+    assert_eq!(editscript.distance, 0);
+    let editscript = shortest_edit_script_with("pidieron", "pedir", &options);
+    assert_eq!(format!("{}", editscript), "=[p]~[i>e]=[di]-[eron]+[r]");
+    assert_eq!(editscript.distance, 3 + 4 + 2);
+}
+
+#[test]
+fn test0034_costmodel_from_str() {
+    let costs = CostModel::from_str("# comment\ndeletion\t2\né\te\t0\n\t-\t5\n").unwrap();
+    assert_eq!(costs.deletion, 2);
+    assert_eq!(costs.insertion, 1);
+    assert_eq!(costs.pair_cost("é", "e"), Some(0));
+    assert_eq!(costs.insertion_cost("a-", Unit::Char), 6);
+    assert!(CostModel::from_str("deletion\ttwo").is_err());
+    assert!(CostModel::from_str("swap\t2").is_err());
+}
+
+#[test]
+fn test0035_algorithms() {
+    let options = DiffOptions {
+        algorithm: Algorithm::Myers,
+        allow_substitutions: false,
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with("kitten", "sitting", &options);
+    assert_eq!(editscript.distance, 5);
+    let options = DiffOptions {
+        algorithm: Algorithm::Levenshtein,
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with("kitten", "sitting", &options);
+    assert_eq!(format!("{}", editscript), "~[k>s]=[itt]~[e>i]=[n]+[g]");
+    assert_eq!(editscript.distance, 3);
+    let editscript = shortest_edit_script_with("pidieron", "pedir", &options);
+    assert_eq!(editscript.distance, 4);
+    assert_eq!(editscript.apply_to("pidieron", None).unwrap(), "pedir");
+    assert_eq!(Algorithm::from_str("Levenshtein").unwrap(), Algorithm::Levenshtein);
+    assert!(Algorithm::from_str("patience").is_err());
+}
+
+#[test]
+fn test0036_custom_backend() {
+    //a backend that replaces everything
+    struct Replace;
+    impl DiffBackend for Replace {
+        fn diff(&self, source: &str, target: &str) -> Vec<DiffOp> {
+            vec![DiffOp::Delete(0..source.len()), DiffOp::Insert(0..target.len())]
+        }
+    }
+    let editscript =
+        shortest_edit_script_with_backend("hablaron", "hablar", &DiffOptions::default(), &Replace);
+    assert_eq!(format!("{}", editscript), "-[hablaron]+[hablar]");
+}
+
+#[test]
+fn test0037_tie_breaking() {
+    let mut options = DiffOptions {
+        tie_breaking: Some(TieBreaking::Start),
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with("stopping", "stoping", &options);
+    assert_eq!(format!("{}", editscript), "=[sto]-[p]=[ping]");
+    let editscript = shortest_edit_script_with("aabbcc", "abc", &options);
+    assert_eq!(format!("{}", editscript), "-[a]=[a]-[b]=[b]-[c]=[c]");
+    options.tie_breaking = Some(TieBreaking::End);
+    let editscript = shortest_edit_script_with("stopping", "stoping", &options);
+    assert_eq!(format!("{}", editscript), "=[stop]-[p]=[ing]");
+    //the same script regardless of the diff algorithm
+    for algorithm in [Algorithm::Dissimilar, Algorithm::Myers, Algorithm::Levenshtein] {
+        options.algorithm = algorithm;
+        let editscript = shortest_edit_script_with("aabbcc", "abc", &options);
+        assert_eq!(format!("{}", editscript), "=[a]-[a]=[b]-[b]=[c]-[c]");
+    }
+    //in suffix mode start and end still refer to the original string
+    options.mode = Mode::Suffix;
+    let editscript = shortest_edit_script_with("stopping", "stoping", &options);
+    assert_eq!(format!("{}", editscript), "=[ing]-[p]");
+}
+
+#[test]
+fn test0038_tie_breaking_grouped() {
+    let options = DiffOptions {
+        algorithm: Algorithm::Myers,
+        tie_breaking: Some(TieBreaking::Grouped),
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with("aabbcc", "abc", &options);
+    assert_eq!(format!("{}", editscript), "=[a]-[a]=[b]-[bc]=[c]");
+    assert_eq!(TieBreaking::from_str("End").unwrap(), TieBreaking::End);
+    assert!(TieBreaking::from_str("middle").is_err());
+}
+
+#[test]
+fn test0039_levenshtein() {
+    assert_eq!(levenshtein("kitten", "sitting"), 6);
+    assert_eq!(levenshtein("", "abc"), 3);
+    assert_eq!(levenshtein("pidieron", "pedir"), 6);
+    assert_eq!(levenshtein("говорим", "говорить"), 3);
+    assert_eq!(
+        levenshtein("hablaron", "hablar"),
+        shortest_edit_script("hablaron", "hablar", false, false, true).distance
+    );
+    //longer than a single machine word
+    let a = "ab".repeat(50);
+    let b = "ba".repeat(50);
+    assert_eq!(levenshtein(&a, &b), 2);
+}
+
+#[test]
+fn test0040_levenshtein_bounded() {
+    assert_eq!(levenshtein_bounded("kitten", "sitting", 6), Some(6));
+    assert_eq!(levenshtein_bounded("kitten", "sitting", 5), None);
+    assert_eq!(levenshtein_bounded("a", "abcdef", 4), None);
+    assert_eq!(levenshtein_bounded("same", "same", 0), Some(0));
+}
+
+#[test]
+fn test0041_invert() {
+    let editscript = shortest_edit_script("pidieron", "pedir", false, false, true);
+    let inverse = editscript.invert().unwrap();
+    assert_eq!(format!("{}", inverse), "=[p]~[e>i]=[di]+[eron]-[r]");
+    assert_eq!(inverse.apply_to("pedir", None).unwrap(), "pidieron");
+    let editscript = shortest_edit_script_suffix("hablaron", "hablar", true, true);
+    let inverse = editscript.invert().unwrap();
+    assert_eq!(inverse.mode, Mode::Suffix);
+    assert_eq!(inverse.apply_to("hablar", None).unwrap(), "hablaron");
+    let editscript = shortest_edit_script("gelopen", "lopen", true, false, true);
+    let inverse = editscript.invert().unwrap();
+    assert_eq!(inverse.apply_to("lopen", None).unwrap(), "gelopen");
+    let editscript: EditScript<String> = EditScript::from_str("=[t]%[eh]").unwrap();
+    assert_eq!(format!("{}", editscript.invert().unwrap()), "=[t]%[he]");
+}
+
+#[test]
+fn test0042_invert_lossy() {
+    let editscript: EditScript<String> = EditScript::from_str("-[a|i|o]+[e]").unwrap();
+    let err = editscript.invert().unwrap_err();
+    assert_eq!(err.to_string(), "Unable to invert -[a|i|o], it is not known which of the options applies");
+    let editscript: EditScript<String> = EditScript::from_str("_[*]=[paris]").unwrap();
+    assert!(editscript.invert().is_err());
+}
+
+#[test]
+fn test0043_compose() {
+    let first = shortest_edit_script_suffix("hablabas", "hablaba", false, true);
+    let second = shortest_edit_script_suffix("hablaba", "hablar", false, true);
+    let composed = first.compose(&second).unwrap();
+    assert_eq!(format!("{}", composed), "-[bas]+[r]");
+    assert_eq!(composed.mode, Mode::Suffix);
+    assert_eq!(composed.apply_to("hablabas", None).unwrap(), "hablar");
+    let first = shortest_edit_script("pidieron", "pedieron", false, false, true).to_owned();
+    let second = shortest_edit_script("pedieron", "pedir", false, false, true).to_owned();
+    let composed = first.compose(&second).unwrap();
+    assert_eq!(format!("{}", composed), "=[p]~[i>e]=[di]-[eron]+[r]");
+    assert_eq!(composed.distance, 6);
+    //abstract edit scripts
+    let first = shortest_edit_script("gelopen", "lopen", true, true, true).to_owned();
+    let second = shortest_edit_script("lopen", "loop", true, true, true).to_owned();
+    let composed = first.compose(&second).unwrap();
+    assert_eq!(composed.apply_to("gelopen", Some(Mode::Prefix)).unwrap(), "loop");
+}
+
+#[test]
+fn test0044_compose_undefined() {
+    let first: EditScript<String> = EditScript::from_str("=[a]").unwrap();
+    let second: EditScript<String> = EditScript::from_str("=[b]").unwrap();
+    assert!(first.compose(&second).is_err());
+    let mut second = first.clone();
+    second.mode = Mode::Suffix;
+    assert!(first.compose(&second).is_err());
+    let second: EditScript<String> = EditScript::from_str("=[a]-[b]").unwrap();
+    assert!(first.compose(&second).is_err());
+    let second: EditScript<String> = EditScript::from_str("+[a|b]").unwrap();
+    assert!(first.compose(&second).is_err());
+}
+
+#[test]
+fn test0045_canonicalize() {
+    let mut script: EditScript<String> = EditScript::from_str("=[ab]=[c]+[x]-[d]=[#0]-[e]=[#1]=[#2]").unwrap();
+    script.instructions.insert(3, EditInstruction::Insertion(String::new()));
+    assert_eq!(format!("{}", script.canonicalize()), "=[abc]-[de]+[x]=[#3]");
+    let script: EditScript<String> = EditScript::from_str("=[p]-[i]+[e]=[dir]").unwrap();
+    assert_eq!(format!("{}", script.canonicalize()), "=[p]~[i>e]=[dir]");
+    let script: EditScript<String> = EditScript::from_str("=[t]-[e]+[h]-[h]+[e]").unwrap();
+    assert_eq!(format!("{}", script.canonicalize()), "=[t]%[eh]");
+    //in suffix mode, the instructions run from the end of the string
+    let mut script: EditScript<String> = EditScript::from_str("-[s]-[a]=[b]=[a]").unwrap();
+    script.mode = Mode::Suffix;
+    let canonical = script.canonicalize();
+    assert_eq!(format!("{}", canonical), "-[as]=[ab]");
+    assert_eq!(canonical.apply_to("habas", None).unwrap(), script.apply_to("habas", None).unwrap());
+}
+
+#[test]
+fn test0046_semantically_eq() {
+    let computed = shortest_edit_script("pidieron", "pedieron", false, false, true);
+    let parsed: EditScript<String> = EditScript::from_str("=[p]-[i]+[e]=[di]=[eron]").unwrap();
+    assert!(parsed.semantically_eq(&computed));
+    assert_ne!(format!("{}", parsed), format!("{}", computed));
+    let other: EditScript<String> = EditScript::from_str("=[p]-[i]+[a]=[dieron]").unwrap();
+    assert!(!other.semantically_eq(&computed));
+    let mut suffix = parsed.clone();
+    suffix.mode = Mode::Suffix;
+    assert!(!suffix.semantically_eq(&parsed));
+}
+
+#[test]
+fn test0047_generalize() {
+    let scripts: Vec<EditScript<String>> = ["hablaban", "comemos", "hablaron", "cantaste"]
+        .iter()
+        .zip(["hablar", "comer", "hablar", "cantar"].iter())
+        .map(|(source, target)| shortest_edit_script_suffix(source, target, false, true).to_owned())
+        .collect();
+    let generalizations = generalize(&scripts);
+    assert_eq!(generalizations.len(), 2);
+    assert_eq!(format!("{}", generalizations[0].script), "-[ban|mos|ste]+[r]");
+    assert_eq!(generalizations[0].covers, vec![0, 1, 3]);
+    assert_eq!(generalizations[0].script.mode, Mode::Suffix);
+    assert_eq!(format!("{}", generalizations[1].script), "-[on]");
+    assert_eq!(generalizations[1].covers, vec![2]);
+    assert_eq!(generalizations[0].script.apply_to("comemos", None).unwrap(), "comer");
+    assert_eq!(generalizations[0].script.apply_to("cantaste", None).unwrap(), "cantar");
+}
+
+#[test]
+fn test0048_generalize_identity_options() {
+    let scripts: Vec<EditScript<String>> = ["=[p]-[i]+[e]=[dir]", "=[p]~[i>e]=[gar]", "=[p]-[a]+[e]=[dir]", "=[p]-[i]+[a]=[dir]"]
+        .iter()
+        .map(|s| EditScript::from_str(s).unwrap())
+        .collect();
+    let generalizations = generalize(&scripts);
+    assert_eq!(generalizations.len(), 2);
+    assert_eq!(format!("{}", generalizations[0].script), "=[p]-[i|a]+[e]=[dir|gar]");
+    assert_eq!(generalizations[0].covers, vec![0, 1, 2]);
+    assert_eq!(format!("{}", generalizations[1].script), "=[p]~[i>a]=[dir]");
+    assert_eq!(generalizations[1].covers, vec![3]);
+}
+
+#[test]
+fn test0049_context() {
+    let options = DiffOptions {
+        context: Some(2),
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with("zerbrochen", "zerbrechen", &options);
+    assert_eq!(format!("{}", editscript), "=[#3]=[br]~[o>e]=[ch]=[#2]");
+    assert_eq!(editscript.distance, 1);
+    assert_eq!(editscript.apply_to("zerbrochen", None).unwrap(), "zerbrechen");
+    assert_eq!(editscript.apply_to("aufbrochen", None).unwrap(), "aufbrechen");
+    assert!(editscript.apply_to("zersprochen", None).is_err());
+    //the context takes precedence over abstraction
+    let options = DiffOptions {
+        generic: true,
+        context: Some(10),
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with("zerbrochen", "zerbrechen", &options);
+    assert_eq!(format!("{}", editscript), "=[zerbr]~[o>e]=[chen]");
+}
+
+#[test]
+fn test0050_context_suffix() {
+    let options = DiffOptions {
+        mode: Mode::Suffix,
+        context: Some(1),
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with("zerbrochen", "zerbrechen", &options);
+    assert_eq!(format!("{}", editscript), "=[#3]=[c]~[o>e]");
+    assert_eq!(editscript.apply_to("gesprochen", None).unwrap(), "gesprechen");
+    let options = DiffOptions {
+        mode: Mode::Suffix,
+        context: Some(0),
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with("zerbrochen", "zerbrechen", &options);
+    assert_eq!(format!("{}", editscript), "=[#4]~[o>e]");
+}
+
+#[test]
+fn test0051_wildcard() {
+    let editscript: EditScript<String> = EditScript::from_str("=[*]-[on]").unwrap();
+    assert_eq!(editscript.instructions[0], EditInstruction::WildcardIdentity);
+    assert_eq!(format!("{}", editscript), "=[*]-[on]");
+    assert_eq!(editscript.distance, 2);
+    assert_eq!(editscript.apply_to("hablaron", None).unwrap(), "hablar");
+    assert_eq!(editscript.apply_to("comieron", None).unwrap(), "comier");
+    assert_eq!(editscript.apply_to("on", None).unwrap(), "");
+    assert!(editscript.apply_to("hablar", None).is_err());
+    //the wildcard matches as much as the remaining instructions allow
+    let editscript: EditScript<String> = EditScript::from_str("=[*]-[a]=[*]").unwrap();
+    assert_eq!(editscript.apply_to("banana", None).unwrap(), "banan");
+    let editscript: EditScript<String> = EditScript::from_str("=[*]~[o>e]=[#3]").unwrap();
+    assert_eq!(editscript.apply_to("zerbrochen", Some(Mode::Prefix)).unwrap(), "zerbrechen");
+    assert_eq!(editscript.apply_to("aufgebrochen", Some(Mode::Suffix)).unwrap(), "aufgebrechen");
+    let editscript: EditScript<String> = EditScript::from_str("-[a]=[*]-[a]").unwrap();
+    assert_eq!(editscript.apply_to("xaxax", Some(Mode::Infix)).unwrap(), "xxx");
+}
+
+#[test]
+fn test0052_wildcard_generation() {
+    let options = DiffOptions {
+        context: Some(1),
+        wildcards: true,
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with("zerbrochen", "zerbrechen", &options);
+    assert_eq!(format!("{}", editscript), "=[*]=[r]~[o>e]=[c]=[*]");
+    assert_eq!(editscript.apply_to("aufgebrochen", None).unwrap(), "aufgebrechen");
+    //only abstracted parts become wildcards
+    let options = DiffOptions {
+        wildcards: true,
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with("zerbrochen", "zerbrechen", &options);
+    assert_eq!(format!("{}", editscript), "=[zerbr]~[o>e]=[chen]");
+}
+
+#[test]
+fn test0053_classes() {
+    let classes = CharClasses::from_str("V\taeiou\n# consonants\nC\tb c d f g h j k l m n p q r s t v w x y z ch\n").unwrap();
+    assert_eq!(classes.contains("V", "e"), Some(true));
+    assert_eq!(classes.contains("C", "ch"), Some(true));
+    assert_eq!(classes.contains("C", "a"), Some(false));
+    assert_eq!(classes.contains("X", "a"), None);
+    let options = ApplyOptions {
+        mode: Some(Mode::Suffix),
+        classes,
+        ..Default::default()
+    };
+    let editscript: EditScript<String> = EditScript::from_str("-[n]-[{V}]").unwrap();
+    assert_eq!(editscript.instructions[1], EditInstruction::DeletionClass("V".to_string()));
+    assert_eq!(format!("{}", editscript), "-[n]-[{V}]");
+    assert_eq!(editscript.distance, 2);
+    assert_eq!(editscript.apply_with("hablaron", &options).unwrap(), "hablar");
+    assert_eq!(editscript.apply_with("comen", &options).unwrap(), "com");
+    assert!(editscript.apply_with("hablar", &options).is_err());
+    let editscript: EditScript<String> = EditScript::from_str("=[e]=[{C}]+[-]").unwrap();
+    assert_eq!(editscript.apply_with("noche", &options).unwrap(), "no-che");
+    let options = ApplyOptions {
+        mode: Some(Mode::Prefix),
+        ..options
+    };
+    let editscript: EditScript<String> = EditScript::from_str("=[{C}]-[{V}]").unwrap();
+    assert_eq!(editscript.apply_with("chico", &options).unwrap(), "chco");
+    assert!(editscript.apply_with("acha", &options).is_err());
+}
+
+#[test]
+fn test0054_classes_undefined() {
+    let editscript: EditScript<String> = EditScript::from_str("=[{V}]").unwrap();
+    match editscript.apply_to("a", None) {
+        Err(ApplyError::UndefinedClass { index, name }) => {
+            assert_eq!(index, 0);
+            assert_eq!(name, "V");
+        }
+        _ => panic!("expected an error about the undefined class"),
+    }
+    assert!(CharClasses::from_str("V aeiou").is_err());
+}
+
+#[test]
+fn test0055_captures() {
+    let editscript: EditScript<String> = EditScript::from_str("=1[*]+[-]&[1]").unwrap();
+    assert_eq!(editscript.instructions[0], EditInstruction::Captured("1".to_string(), Box::new(EditInstruction::WildcardIdentity)));
+    assert_eq!(editscript.instructions[2], EditInstruction::BackReference("1".to_string()));
+    assert_eq!(format!("{}", editscript), "=1[*]+[-]&[1]");
+    assert_eq!(editscript.apply_to("buku", None).unwrap(), "buku-buku");
+    assert_eq!(editscript.apply_to("rumah", Some(Mode::Suffix)).unwrap(), "rumah-rumah");
+    //back-references may precede the capture
+    let editscript: EditScript<String> = EditScript::from_str("&[x]=x[n]").unwrap();
+    assert_eq!(editscript.apply_to("ten", Some(Mode::Suffix)).unwrap(), "tenn");
+    let editscript: EditScript<String> = EditScript::from_str("=[te]=x[n]&[x]").unwrap();
+    assert_eq!(editscript.apply_to("ten", None).unwrap(), "tenn");
+    assert_eq!(editscript.apply_to("kata ten", Some(Mode::Infix)).unwrap(), "kata tenn");
+    let editscript: EditScript<String> = EditScript::from_str("=[a]&[y]").unwrap();
+    assert!(editscript.apply_to("a", None).is_err());
+    assert!(editscript.invert().is_err());
+}
+
+#[test]
+fn test0056_captures_generation() {
+    let options = DiffOptions {
+        captures: true,
+        ..Default::default()
+    };
+    let editscript = shortest_edit_script_with("buku", "buku-buku", &options);
+    assert_eq!(format!("{}", editscript), "=1[buku]+[-]&[1]");
+    assert_eq!(editscript.distance, 2);
+    let editscript = shortest_edit_script_with("ten", "tenn", &options);
+    assert_eq!(format!("{}", editscript), "=[te]=1[n]&[1]");
+    let options = DiffOptions {
+        mode: Mode::Suffix,
+        generic: true,
+        wildcards: true,
+        ..options
+    };
+    let editscript = shortest_edit_script_with("buku", "buku-buku", &options);
+    assert_eq!(format!("{}", editscript), "=1[*]+[-]&[1]");
+    assert_eq!(editscript.apply_to("rumah", None).unwrap(), "rumah-rumah");
+    let editscript = shortest_edit_script_with("ten", "tenn", &options);
+    assert_eq!(format!("{}", editscript), "&[1]=1[#1]");
+    assert_eq!(editscript.apply_to("stop", None).unwrap(), "stopp");
+}
+
+#[test]
+fn test0057_escaping() {
+    let editscript = shortest_edit_script("arr[0]", "arr[1]|x", false, false, true);
+    assert_eq!(format!("{}", editscript), "=[arr[]-[0\\]]+[1\\]\\|x]");
+    let parsed: EditScript<String> = EditScript::from_str(&editscript.to_string()).unwrap();
+    assert_eq!(parsed.instructions, editscript.to_owned().instructions);
+    //a leading # or * is escaped so it is not taken for a length or a wildcard
+    let editscript: EditScript<String> = EditScript::from_str("=[\\#12]-[\\*]").unwrap();
+    assert_eq!(editscript.instructions[0], EditInstruction::Identity("#12".to_string()));
+    assert_eq!(editscript.instructions[1], EditInstruction::Deletion("*".to_string()));
+    assert_eq!(format!("{}", editscript), "=[\\#12]-[*]");
+    assert_eq!(editscript.apply_to("#12*", None).unwrap(), "#12");
+    let editscript: EditScript<String> = EditScript::from_str("~[a\\>b>c\\\\]").unwrap();
+    assert_eq!(editscript.instructions[0], EditInstruction::Substitution("a>b".to_string(), "c\\".to_string()));
+}
+
+#[test]
+fn test0058_parse_errors() {
+    let err = EditScript::<String>::from_str("=[a]xyz").unwrap_err();
+    assert_eq!(err, ParseError::Syntax { offset: 4, charoffset: 4, expected: Expected::Operator, found: Some('x') });
+    let err = EditScript::<String>::from_str("=[ä]+[ö").unwrap_err();
+    assert_eq!(err, ParseError::Syntax { offset: 9, charoffset: 7, expected: Expected::ClosingBracket, found: None });
+    assert_eq!(err.to_string(), "Expected ] at character 8 of edit script, found the end");
+    let err = EditScript::<String>::from_str("é[a]").unwrap_err();
+    assert_eq!(err, ParseError::Syntax { offset: 0, charoffset: 0, expected: Expected::Operator, found: Some('é') });
+    match EditScript::<String>::from_str("=[a]~[ab]").unwrap_err() {
+        ParseError::Syntax { offset, expected, .. } => {
+            assert_eq!(offset, 8);
+            assert_eq!(expected, Expected::SubstitutionSeparator);
+        }
+        err => panic!("unexpected error {:?}", err),
+    }
+    assert_eq!(EditScript::<String>::from_str("").unwrap_err(), ParseError::Empty);
+    assert!(EditScript::<String>::from_str("+[]").is_err());
+    assert!(EditInstruction::<String>::from_str("=[a]=[b]").is_err());
+    //the target of a substitution may contain an unescaped >
+    let instruction = EditInstruction::<String>::from_str("~[a>b>c]").unwrap();
+    assert_eq!(instruction, EditInstruction::Substitution("a".to_string(), "b>c".to_string()));
+    //neither side of a substitution may be empty
+    let err = EditScript::<String>::from_str("~[>]").unwrap_err();
+    assert_eq!(err, ParseError::Syntax { offset: 2, charoffset: 2, expected: Expected::Payload, found: Some('>') });
+    let err = EditScript::<String>::from_str("=[ä]~[a>]").unwrap_err();
+    assert_eq!(err, ParseError::Syntax { offset: 9, charoffset: 8, expected: Expected::Payload, found: Some(']') });
+    let err = EditScript::<String>::from_str("~[>b]").unwrap_err();
+    assert_eq!(err, ParseError::Syntax { offset: 2, charoffset: 2, expected: Expected::Payload, found: Some('>') });
+    //a length that does not fit is an error rather than literal text
+    let err = EditScript::<String>::from_str("=[#99999999999]").unwrap_err();
+    assert_eq!(err, ParseError::Syntax { offset: 3, charoffset: 3, expected: Expected::Length, found: Some('9') });
+    for operator in ['_', '^'] {
+        let err = EditScript::<String>::from_str(&format!("=[ä]{}[#4294967296]", operator)).unwrap_err();
+        assert_eq!(err, ParseError::Syntax { offset: 8, charoffset: 7, expected: Expected::Length, found: Some('4') });
+    }
+    assert_eq!(EditInstruction::<String>::from_str("=[#4294967295]").unwrap(), EditInstruction::GenericIdentity(u32::MAX));
+    assert_eq!(EditInstruction::<String>::from_str("=[#x9]").unwrap(), EditInstruction::Identity("#x9".to_string()));
+}
+
+#[test]
+fn test0059_apply_errors() {
+    let editscript: EditScript<String> = EditScript::from_str("-[eron]+[r]=[dx]-[i]+[e]").unwrap();
+    let err = editscript.apply_to("pidieron", Some(Mode::Suffix)).unwrap_err();
+    assert_eq!(
+        err,
+        ApplyError::NoMatch { index: 2, position: 2, expected: "dx".to_string(), found: "di".to_string() }
+    );
+    assert!(!err.is_permanent());
+    assert_eq!(err.to_string(), "Edit script does not match, instruction 3 expects 'dx' at position 3 but found 'di'");
+    //positions are character offsets, after a wildcard the error for its shortest match is reported
+    let editscript: EditScript<String> = EditScript::from_str("=[ä]=[*]-[ß]").unwrap();
+    match editscript.apply_to("äöü", None).unwrap_err() {
+        ApplyError::NoMatch { index, position, found, .. } => {
+            assert_eq!(index, 2);
+            assert_eq!(position, 1);
+            assert_eq!(found, "ö");
+        }
+        err => panic!("unexpected error {:?}", err),
+    }
+    let editscript: EditScript<String> = EditScript::from_str("=[a]+[x|y]").unwrap();
+    let err = editscript.apply_to("aha", Some(Mode::Infix)).unwrap_err();
+    assert_eq!(err, ApplyError::Ambiguous { index: 1 });
+    assert!(err.is_permanent());
+    //token-level edit scripts report token offsets
+    let source = ["the", "big", "dog"];
+    let target = ["the", "small", "dog"];
+    let editscript = shortest_edit_script_tokens(&source, &target, false, false, true);
+    match editscript.apply_to(&["a", "big", "dog"], None).unwrap_err() {
+        ApplyError::NoMatch { index, position, expected, found } => {
+            assert_eq!((index, position), (0, 0));
+            assert_eq!(expected, "the");
+            assert_eq!(found, "a");
+        }
+        err => panic!("unexpected error {:?}", err),
+    }
+}
+
+#[test]
+fn test0060_apply_traced() {
+    let editscript: EditScript<String> = EditScript::from_str("-[eron]+[r]=[di]-[i]+[e]").unwrap();
+    let options = ApplyOptions { mode: Some(Mode::Suffix), ..Default::default() };
+    let (output, trace) = editscript.apply_traced("pidieron", &options).unwrap();
+    assert_eq!(output, "pedir");
+    assert_eq!(trace.len(), 5);
+    assert_eq!(trace[0].instruction, "-[eron]");
+    assert_eq!(trace[0].source, 4..8);
+    assert_eq!(trace[0].consumed, "eron");
+    assert_eq!(trace[1].target, 4..5);
+    assert_eq!(trace[1].produced, "r");
+    assert_eq!(trace[2].head, "pi");
+    assert_eq!(trace[2].tail, "dir");
+    //steps after a wildcard are located in the full input and output
+    let editscript: EditScript<String> = EditScript::from_str("=[ä]=[*]~[n>m]").unwrap();
+    let (output, trace) = editscript.apply_traced("äpfeln", &ApplyOptions::default()).unwrap();
+    assert_eq!(output, "äpfelm");
+    assert_eq!(trace[1].source, 1..5);
+    assert_eq!(trace[2].source, 5..6);
+    assert_eq!(trace[2].target, 5..6);
+    assert_eq!(trace[2].head, "äpfelm");
+    assert_eq!(trace[2].tail, "");
+    let editscript: EditScript<String> = EditScript::from_str("-[a]+[o]").unwrap();
+    let options = ApplyOptions { mode: Some(Mode::Infix), ..Default::default() };
+    let (_, trace) = editscript.apply_traced("haha", &options).unwrap();
+    assert_eq!(trace.iter().map(|step| step.source.clone()).collect::<Vec<_>>(), vec![1..2, 2..2, 3..4, 4..4]);
+}
+
+#[test]
+fn test0061_matches() {
+    let editscript: EditScript<String> = EditScript::from_str("-[eron]+[r]=[di]-[i]+[e]").unwrap();
+    assert_eq!(editscript.matches("pidieron", Some(Mode::Suffix)), Some(1..8));
+    assert_eq!(editscript.matches("pidiera", Some(Mode::Suffix)), None);
+    assert_eq!(editscript.matches("pidieron", Some(Mode::Normal)), None);
+    let editscript: EditScript<String> = EditScript::from_str("=[ä]=[*]~[n>m]").unwrap();
+    assert_eq!(editscript.matches("äpfeln", None), Some(0..7));
+    assert_eq!(editscript.matches("äpfeln!", Some(Mode::Prefix)), Some(0..7));
+    assert_eq!(editscript.matches("äpfel", None), None);
+    //infix mode returns the first occurrence
+    let editscript: EditScript<String> = EditScript::from_str("-[a]+[o]").unwrap();
+    assert_eq!(editscript.matches("hahaha", Some(Mode::Infix)), Some(1..2));
+    assert_eq!(editscript.matches("hihihi", Some(Mode::Infix)), None);
+    //instructions that can never be applied
+    let editscript: EditScript<String> = EditScript::from_str("=[a]+[x|y]").unwrap();
+    assert_eq!(editscript.matches("a", None), None);
+    let editscript: EditScript<String> = EditScript::from_str("=[{V}]-[s]").unwrap();
+    assert_eq!(editscript.matches("as", None), None);
+    //agrees with applying
+    for (script, input) in [("^[#1]=[A]-[b]", "abc"), ("=1[ab]+[x]&[1]", "abc"), ("-[d]=[*]-[é]", "dabé")] {
+        let editscript: EditScript<String> = EditScript::from_str(script).unwrap();
+        for mode in [Mode::Normal, Mode::Prefix, Mode::Suffix, Mode::Infix] {
+            assert_eq!(editscript.matches(input, Some(mode)).is_some(), editscript.apply_to(input, Some(mode)).is_ok());
+        }
+    }
+}
+
+#[test]
+fn test0062_apply_all() {
+    let options = ApplyOptions { mode: Some(Mode::Infix), ..Default::default() };
+    let editscript: EditScript<String> = EditScript::from_str("=[l]+[a|o]").unwrap();
+    assert_eq!(editscript.apply_all("hello", &options).unwrap(), vec!["helalao", "helaloo", "helolao", "heloloo"]);
+    //all segmentations of overlapping occurrences
+    let editscript: EditScript<String> = EditScript::from_str("-[aa]+[b]").unwrap();
+    assert_eq!(editscript.apply_all("aaa", &options).unwrap(), vec!["ab", "ba"]);
+    assert_eq!(editscript.apply_all("aaaa", &options).unwrap(), vec!["aba", "bb"]);
+    let editscript: EditScript<String> = EditScript::from_str("-[a|aa]+[b]").unwrap();
+    assert_eq!(editscript.apply_all("aaa", &options).unwrap(), vec!["bb", "bbb"]);
+    //deletion options are all explored, duplicates are removed
+    let editscript: EditScript<String> = EditScript::from_str("-[n|en]").unwrap();
+    let options = ApplyOptions { mode: Some(Mode::Suffix), ..Default::default() };
+    assert_eq!(editscript.apply_all("lopen", &options).unwrap(), vec!["lop", "lope"]);
+    let editscript: EditScript<String> = EditScript::from_str("-[e|é]+[e|é]").unwrap();
+    assert_eq!(editscript.apply_all("cafe", &options).unwrap(), vec!["cafe", "café"]);
+    let mut classes = CharClasses::new();
+    classes.define("V", "a e ie");
+    let options = ApplyOptions { mode: Some(Mode::Suffix), classes, ..Default::default() };
+    let editscript: EditScript<String> = EditScript::from_str("-[{V}]").unwrap();
+    assert_eq!(editscript.apply_all("pie", &options).unwrap(), vec!["p", "pi"]);
+    //errors are reported if no variant applies
+    let editscript: EditScript<String> = EditScript::from_str("-[x|y]").unwrap();
+    assert!(matches!(editscript.apply_all("abc", &ApplyOptions::default()), Err(ApplyError::NoMatch { index: 0, .. })));
+}
+
+#[test]
+fn test0063_option_matching() {
+    let editscript: EditScript<String> = EditScript::from_str("-[n|en]").unwrap();
+    let mut options = ApplyOptions { mode: Some(Mode::Suffix), ..Default::default() };
+    assert_eq!(editscript.apply_with("lopen", &options).unwrap(), "lope");
+    options.option_matching = OptionMatching::Longest;
+    assert_eq!(editscript.apply_with("lopen", &options).unwrap(), "lop");
+    assert_eq!(editscript.matches_with("lopen", &options), Some(3..5));
+    options.option_matching = OptionMatching::Shortest;
+    assert_eq!(editscript.apply_with("lopen", &options).unwrap(), "lope");
+    //the next alternative is tried if the preferred one makes the rest fail
+    let editscript: EditScript<String> = EditScript::from_str("-[n|en]=[p]").unwrap();
+    assert_eq!(editscript.apply_with("lopen", &options).unwrap(), "lop");
+    assert_eq!(editscript.matches_with("lopen", &options), Some(2..5));
+    let editscript: EditScript<String> = EditScript::from_str("=[ab|a]-[b]").unwrap();
+    for option_matching in [OptionMatching::First, OptionMatching::Longest, OptionMatching::Shortest] {
+        let options = ApplyOptions { option_matching, ..Default::default() };
+        assert_eq!(editscript.apply_with("ab", &options).unwrap(), "a");
+    }
+    let options = ApplyOptions { mode: Some(Mode::Infix), option_matching: OptionMatching::Longest, ..Default::default() };
+    let editscript: EditScript<String> = EditScript::from_str("-[a|aa]+[o]").unwrap();
+    assert_eq!(editscript.apply_with("baaab", &options).unwrap(), "boob");
+    assert_eq!(OptionMatching::from_str("Shortest").unwrap(), OptionMatching::Shortest);
+    assert!(OptionMatching::from_str("greedy").is_err());
+}
+
+#[test]
+fn test0064_wildcards_insertion() {
+    //an insertion keeps an unchanged part of fixed length to be placed relative to
+    for mode in [Mode::Normal, Mode::Suffix, Mode::Prefix] {
+        for generic in [false, true] {
+            let options = DiffOptions { mode, generic, wildcards: true, ..Default::default() };
+            for (source, target) in [(">\\ß", ">\\aß"), ("ßaßbß", "ßaßabbß"), ("lopen", "gelopen"), ("kat", "katten")] {
+                let editscript = shortest_edit_script_with(source, target, &options);
+                assert_eq!(editscript.apply_to(source, None).unwrap(), target, "{} {:?}", editscript, mode);
+                let inverse = editscript.to_owned().invert().unwrap();
+                assert_eq!(inverse.apply_to(target, None).unwrap(), source, "{} {:?}", inverse, mode);
+            }
+        }
+    }
+    let options = DiffOptions { generic: true, wildcards: true, ..Default::default() };
+    let editscript = shortest_edit_script_with(">\\ß", ">\\aß", &options);
+    assert_eq!(format!("{}", editscript), "=[*]+[a]=[#1]");
+}
+
+#[test]
+fn test0065_infix_empty_input() {
+    //an empty input has a single position to match at
+    let editscript: EditScript<String> = EditScript::from_str("=[x]").unwrap();
+    assert!(matches!(editscript.apply_to("", Some(Mode::Infix)), Err(ApplyError::NoMatch { index: 0, position: 0, .. })));
+    assert_eq!(editscript.matches("", Some(Mode::Infix)), None);
+    let options = ApplyOptions { mode: Some(Mode::Infix), ..Default::default() };
+    assert!(editscript.apply_all("", &options).is_err());
+    let editscript: EditScript<String> = EditScript::from_str("+[x]").unwrap();
+    assert_eq!(editscript.apply_to("", Some(Mode::Infix)).unwrap(), "x");
+    assert_eq!(editscript.matches("", Some(Mode::Infix)), Some(0..0));
+    assert_eq!(editscript.apply_all("", &options).unwrap(), vec!["x"]);
+}
+
+#[test]
+fn test0066_option_matching_multiple() {
+    let apply = |script: &str, input: &str, mode: Mode, option_matching: OptionMatching| {
+        let editscript: EditScript<String> = EditScript::from_str(script).unwrap();
+        let options = ApplyOptions { mode: Some(mode), option_matching, ..Default::default() };
+        let output = editscript.apply_with(input, &options).unwrap();
+        assert!(editscript.matches_with(input, &options).is_some());
+        output
+    };
+    //several options in suffix mode, backtracking over the earlier one
+    assert_eq!(apply("-[en|n]=[pe|p]-[o]", "lopen", Mode::Suffix, OptionMatching::First), "lp");
+    assert_eq!(apply("-[en|n]=[pe|p]-[o]", "lopen", Mode::Suffix, OptionMatching::Shortest), "lpe");
+    assert_eq!(apply("-[n|en]=[e|pe]-[o]", "lopen", Mode::Suffix, OptionMatching::Longest), "lpe");
+    //backtracking over the later one
+    assert_eq!(apply("-[n]=[e|pe]-[p]", "lopen", Mode::Suffix, OptionMatching::Longest), "loe");
+    //several options in infix mode
+    assert_eq!(apply("-[a|aa]=[b|bb]+[x]", "caabbd", Mode::Infix, OptionMatching::Longest), "cbbxd");
+    assert_eq!(apply("-[a|aa]=[b|bb]+[x]", "caabbd", Mode::Infix, OptionMatching::Shortest), "cbxbd");
+    assert_eq!(apply("-[a|aa]=[b|bb]+[x]", "caabbd", Mode::Infix, OptionMatching::First), "cbxbd");
+    //token-level edit scripts follow the same policy
+    let input = ["a", "b", "c"];
+    let editscript = EditScript {
+        mode: Mode::Suffix,
+        distance: 1,
+        instructions: vec![EditInstruction::DeletionOptions(vec![TokenSeq(&input[2..]), TokenSeq(&input[1..])])],
+    };
+    assert_eq!(editscript.apply_to(&input, None).unwrap(), ["a", "b"]);
+    assert_eq!(editscript.apply_matching(&input, None, OptionMatching::Longest).unwrap(), ["a"]);
+    assert_eq!(editscript.apply_matching(&input, None, OptionMatching::Shortest).unwrap(), ["a", "b"]);
+    let mut editscript = editscript;
+    editscript.instructions.push(EditInstruction::Identity(TokenSeq(&input[..1])));
+    assert_eq!(editscript.apply_to(&input, None).unwrap(), ["a"]);
+    let editscript = EditScript {
+        mode: Mode::Normal,
+        distance: 1,
+        instructions: vec![
+            EditInstruction::Identity(TokenSeq(&input[..1])),
+            EditInstruction::DeletionOptions(vec![TokenSeq(&input[1..2]), TokenSeq(&input[1..])]),
+            EditInstruction::Identity(TokenSeq(&input[2..])),
+        ],
+    };
+    assert_eq!(editscript.apply_matching(&input, None, OptionMatching::Longest).unwrap(), ["a", "c"]);
+}
+
+#[test]
+fn test0067_escaping_roundtrip() {
+    let payloads = ["\\", "]", "|", ">", "a\\]|>b", "#12", "#", "*", "{V}", "{", "#*{", "\\#3", "]]", "[x]", "> <"];
+    for payload in payloads {
+        let s = payload.to_string();
+        let alternatives = vec![s.clone(), "x".to_string()];
+        let instructions = vec![
+            EditInstruction::IdentityOptions(vec![s.clone()]),
+            EditInstruction::DeletionOptions(vec![s.clone()]),
+            EditInstruction::InsertionOptions(vec![s.clone()]),
+            EditInstruction::IdentityOptions(vec![String::new(), s.clone()]),
+            EditInstruction::DeletionOptions(vec![s.clone(), String::new()]),
+            EditInstruction::Identity(s.clone()),
+            EditInstruction::Deletion(s.clone()),
+            EditInstruction::Insertion(s.clone()),
+            EditInstruction::Substitution(s.clone(), s.clone()),
+            EditInstruction::Substitution("a".to_string(), s.clone()),
+            EditInstruction::Transposition(s.clone()),
+            EditInstruction::IdentityOptions(alternatives.clone()),
+            EditInstruction::DeletionOptions(alternatives.iter().rev().cloned().collect()),
+            EditInstruction::InsertionOptions(alternatives.clone()),
+            EditInstruction::Captured("1".to_string(), Box::new(EditInstruction::Identity(s.clone()))),
+        ];
+        for instruction in instructions.iter() {
+            let parsed = EditInstruction::<String>::from_str(&instruction.to_string());
+            assert_eq!(parsed.as_ref(), Ok(instruction), "{:?} as {}", payload, instruction);
+        }
+        //the instructions of a whole edit script round-trip, the mode is not written out
+        let editscript = EditScript { mode: Mode::Normal, distance: 9, instructions };
+        let parsed: EditScript<String> = EditScript::from_str(&editscript.to_string()).unwrap();
+        assert_eq!(parsed.instructions, editscript.instructions);
+        assert_eq!(parsed.mode, Mode::Normal);
+    }
+    assert_eq!(format!("{}", EditInstruction::IdentityOptions(vec!["a"])), "=[|a]");
+    assert_eq!(format!("{}", EditInstruction::DeletionOptions(vec!["", "en"])), "-[||en]");
+    //computed edit scripts in normal mode round-trip as a whole, distance included
+    for (source, target) in [("hablaron", "hablar"), ("pidieron", "pedir"), ("x#1|]", "x#2>]"), ("{V}a", "*b\\"), ("ye olde", "the old")] {
+        for allow_substitutions in [false, true] {
+            let editscript = shortest_edit_script(source, target, false, false, allow_substitutions).to_owned();
+            assert_eq!(EditScript::from_str(&editscript.to_string()), Ok(editscript));
+        }
+    }
+    //computed edit scripts in suffix mode keep their instructions and distance
+    let editscript = shortest_edit_script_suffix("x#1|]", "x#2>]", false, true);
+    let parsed: EditScript<String> = EditScript::from_str(&editscript.to_string()).unwrap();
+    assert_eq!(parsed.instructions, editscript.instructions);
+    assert_eq!(parsed.distance, editscript.distance);
+    assert_eq!(parsed.mode, Mode::Normal);
+}
+
+#[test]
+fn test0068_compose_trailing_identities() {
+    //the second edit script ignores the end of the output of the first one, which still requires its input
+    let first: EditScript<String> = EditScript::from_str("-[a]=[bc]").unwrap();
+    let second: EditScript<String> = EditScript::from_str("=[b]").unwrap();
+    let composed = first.compose(&second).unwrap();
+    assert_eq!(format!("{}", composed), "-[a]=[b]-[c]");
+    assert_eq!(composed.apply_to("abc", None).unwrap(), "b");
+    assert!(first.apply_to("ab", None).is_err());
+    assert!(composed.apply_to("ab", None).is_err());
+    let first: EditScript<String> = EditScript::from_str("=[ab]+[c]").unwrap();
+    let second: EditScript<String> = EditScript::from_str("=[a]").unwrap();
+    let composed = first.compose(&second).unwrap();
+    assert_eq!(format!("{}", composed), "=[a]-[b]");
+    assert!(composed.apply_to("a", None).is_err());
+    //a generic identity can not be required without outputting it
+    let first: EditScript<String> = EditScript::from_str("=[#2]+[c]").unwrap();
+    let second: EditScript<String> = EditScript::from_str("=[#1]").unwrap();
+    let err = first.compose(&second).unwrap_err();
+    assert_eq!(err.to_string(), "Unable to compose edit scripts, the second edit script ignores a generic identity of the first one");
+}
+
+#[test]
+fn test0069_levenshtein_default_options() {
+    //the distance equals that of the default edit script, also where it does not align substitutions
+    let editscript = shortest_edit_script_with("]", "#>>", &DiffOptions::default());
+    assert_eq!(format!("{}", editscript), "-[\\]]+[#\\>\\>]");
+    assert_eq!(editscript.distance, 4);
+    assert_eq!(levenshtein("]", "#>>"), 4);
+    assert_eq!(levenshtein_bounded("]", "#>>", 3), None);
+    for (a, b) in [("]", "#>>"), ("kitten", "sitting"), ("pidieron", "pedir"), ("hablaron", "hablar"), ("abcabc", "aaab"), ("ab", "ba")] {
+        let distance = shortest_edit_script(a, b, false, false, true).distance;
+        assert_eq!(levenshtein(a, b), distance, "{} {}", a, b);
+        assert_eq!(shortest_edit_script_with(a, b, &DiffOptions::default()).distance, distance, "{} {}", a, b);
+        assert_eq!(levenshtein_bounded(a, b, distance), Some(distance), "{} {}", a, b);
+        assert_eq!(levenshtein_bounded(a, b, distance - 1), None, "{} {}", a, b);
+    }
+}
+
+#[test]
+fn test0070_trace_case_length() {
+    //case transformations that change the length of the input in suffix mode
+    let options = ApplyOptions { mode: Some(Mode::Suffix), ..Default::default() };
+    let editscript: EditScript<String> = EditScript::from_str("=[x]_[*]").unwrap();
+    let (output, trace) = editscript.apply_traced("İx", &options).unwrap();
+    assert_eq!(output, "i\u{307}x");
+    assert_eq!(trace[0].source, 2..3);
+    assert_eq!(trace[0].consumed, "x");
+    assert_eq!(trace[1].source, 2..2);
+    assert_eq!(trace[1].head, "i\u{307}");
+    let editscript: EditScript<String> = EditScript::from_str("=[b]_[#1]=[*]").unwrap();
+    let (output, trace) = editscript.apply_traced("aẞb", &options).unwrap();
+    assert_eq!(output, "aßb");
+    assert_eq!(trace[2].consumed, "aß");
+    assert_eq!(trace[2].source, 0..2);
+    //after a wildcard
+    let editscript: EditScript<String> = EditScript::from_str("=[x]=[*]-[a]_[*]").unwrap();
+    let (output, trace) = editscript.apply_traced("İaẞx", &options).unwrap();
+    assert_eq!(output, "i\u{307}ẞx");
+    assert_eq!(trace[0].source, 4..5);
+    assert_eq!(trace[1].consumed, "ẞ");
+    assert_eq!(trace[2].consumed, "a");
+    assert_eq!(trace[2].head, "i\u{307}");
+    assert_eq!(trace[3].source, 2..2);
+}
+
+#[test]
+fn test0071_wildcards_reproduce() {
+    //a greedy wildcard must not move the instructions after it
+    let options = DiffOptions { generic: true, wildcards: true, algorithm: Algorithm::Myers, ..Default::default() };
+    let editscript = shortest_edit_script_with("abcd", "abXcYd", &options);
+    assert_eq!(format!("{}", editscript), "=[*]+[X]=[#1]+[Y]=[#1]");
+    assert_eq!(editscript.apply_to("abcd", None).unwrap(), "abXcYd");
+    let options = DiffOptions { mode: Mode::Suffix, context: Some(2), wildcards: true, captures: true, algorithm: Algorithm::Myers, ..Default::default() };
+    let editscript = shortest_edit_script_with("ab", "abb", &options);
+    assert_eq!(editscript.apply_to("ab", None).unwrap(), "abb");
+    let options = DiffOptions { generic: true, wildcards: true, ..Default::default() };
+    let editscript = shortest_edit_script_with("abb", "aab", &options);
+    assert_eq!(editscript.apply_to("abb", None).unwrap(), "aab");
+    for mode in [Mode::Normal, Mode::Prefix, Mode::Suffix] {
+        for algorithm in [Algorithm::Dissimilar, Algorithm::Myers, Algorithm::Levenshtein] {
+            for captures in [false, true] {
+                let options = DiffOptions { mode, algorithm, generic: true, wildcards: true, captures, ..Default::default() };
+                for (source, target) in [("abcd", "abXcYd"), ("ab", "abb"), ("ab", "aab"), ("abb", "aab"), ("aXbXc", "abc")] {
+                    let editscript = shortest_edit_script_with(source, target, &options);
+                    assert_eq!(editscript.apply_to(source, None).unwrap(), target, "{} {:?}", editscript, options);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test0072_apply_tokens_errors() {
+    //token-level edit scripts report the same errors as edit scripts on strings
+    let tokens = ["x", "y"];
+    let editscript = EditScript { mode: Mode::Normal, distance: 0, instructions: vec![EditInstruction::Identity(TokenSeq(&tokens[..1]))] };
+    let stringscript: EditScript<String> = EditScript::from_str("=[x]").unwrap();
+    for mode in [Mode::Normal, Mode::Prefix, Mode::Suffix, Mode::Infix] {
+        let expected = stringscript.apply_to("", Some(mode)).unwrap_err();
+        assert_eq!(editscript.apply_to(&[], Some(mode)).unwrap_err(), expected, "{:?}", mode);
+    }
+    assert_eq!(
+        editscript.apply_to(&["y"], Some(Mode::Infix)).unwrap_err(),
+        ApplyError::NoMatch { index: 0, position: 0, expected: "x".to_string(), found: "y".to_string() }
+    );
+    let editscript = EditScript { mode: Mode::Normal, distance: 1, instructions: vec![EditInstruction::Insertion(TokenSeq(&tokens[1..]))] };
+    assert_eq!(editscript.apply_to(&[], Some(Mode::Infix)).unwrap(), ["y"]);
+    assert_eq!(editscript.apply_to(&["x"], Some(Mode::Infix)).unwrap(), ["y", "x"]);
+    let editscript: EditScript<TokenSeq<&str>> = EditScript { mode: Mode::Normal, distance: 0, instructions: vec![] };
+    assert_eq!(editscript.apply_to(&tokens, None).unwrap_err(), ApplyError::EmptyScript);
+    assert_eq!(editscript.apply_to(&tokens, Some(Mode::Infix)).unwrap_err(), ApplyError::EmptyScript);
+    assert_eq!(editscript.apply_to(&tokens, Some(Mode::Prefix)).unwrap(), tokens);
+}